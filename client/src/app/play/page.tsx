@@ -8,8 +8,16 @@ import {
   handlePreviewEvent,
   handleErrorEvent,
   handleGameEnd,
+  handleTakebackRequest,
+  handleTakebackDecline,
+  handleDrawOffer,
+  handleDrawDecline,
+  handleRematchOffer,
+  handleRematchStart,
+  handleSpectatorCount,
+  handleChatMessage,
 } from "@/lib/handlers";
-import { Board, Piece, Event } from "@/types";
+import { Board, ChatMessage, Clock, Piece, Event } from "@/types";
 import { useEffect, useState } from "react";
 import Square from "@/components/board/Square";
 import StatusText from "@/components/StatusText";
@@ -18,16 +26,16 @@ import cookie from "cookie";
 import cn from "classnames";
 import simpleGet from "@/lib/simpleGet";
 import call from "@/lib/call";
-import { Button } from "@headlessui/react";
+import { Button, Input } from "@headlessui/react";
 
 const UUID_REGEX =
   /^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$/;
 
-function createBoard(): Board {
+function createBoard(width = 8): Board {
   let board: Board = [];
-  for (let i = 0; i < 8; i++) {
+  for (let i = 0; i < width; i++) {
     board[i] = [];
-    for (let j = 0; j < 8; j++) {
+    for (let j = 0; j < width; j++) {
       board[i][j] = null;
     }
   }
@@ -38,7 +46,8 @@ interface LiveBoardProps {
   gameId: string | null;
 }
 
-function LiveBoard({ gameId }: LiveBoardProps) {
+function LiveBoard({ gameId: initialGameId }: LiveBoardProps) {
+  const [gameId, setGameId] = useState(initialGameId);
   const [aborted, setAborted] = useState(false);
   const [ready, setReady] = useState(false);
   const [setup, setSetup] = useState(false);
@@ -47,6 +56,13 @@ function LiveBoard({ gameId }: LiveBoardProps) {
   const [color, setColor] = useState<Piece>(Piece.Black);
   const [token, setToken] = useState<string>();
   const [preview, setPreview] = useState<Array<[number, number]>>();
+  const [clock, setClock] = useState<Clock>();
+  const [now, setNow] = useState(Date.now());
+  const [spectating, setSpectating] = useState(false);
+  const [spectators, setSpectators] = useState(0);
+  const [messages, setMessages] = useState<ChatMessage[]>([]);
+  const [draft, setDraft] = useState("");
+  const [muted, setMuted] = useState(false);
 
   const { sendJsonMessage } = useWebSocket("ws://localhost:3000/live", {
     onMessage: (msg) => {
@@ -59,6 +75,14 @@ function LiveBoard({ gameId }: LiveBoardProps) {
         5: handlePreviewEvent,
         6: handleErrorEvent,
         7: handleGameEnd,
+        8: handleTakebackRequest,
+        9: handleTakebackDecline,
+        10: handleDrawOffer,
+        11: handleDrawDecline,
+        12: handleRematchOffer,
+        13: handleRematchStart,
+        14: handleSpectatorCount,
+        15: handleChatMessage,
       } as const;
       handlers[data.op]({
         //@ts-expect-error
@@ -66,17 +90,33 @@ function LiveBoard({ gameId }: LiveBoardProps) {
         board,
         token,
         gameId,
+        color,
         aborted,
+        send: sendJsonMessage,
         setReady,
         setAborted,
         setTurn,
         setBoard,
         setPreview,
         setColor,
+        setClock,
+        setGameId,
+        setSpectators,
+        setMessages,
       });
     },
   });
 
+  // Mutes only last for the game they were made in, so a rematch starts unmuted.
+  useEffect(() => setMuted(false), [gameId]);
+
+  // Count the running clock down between updates from the server.
+  useEffect(() => {
+    if (!clock) return;
+    const interval = setInterval(() => setNow(Date.now()), 250);
+    return () => clearInterval(interval);
+  }, [clock]);
+
   useEffect(() => {
     setToken(cookie.parse(document.cookie).sid);
     if (token && !setup) {
@@ -87,18 +127,24 @@ function LiveBoard({ gameId }: LiveBoardProps) {
           type: "Identify",
         },
       });
-      sendJsonMessage({
-        op: 3,
-        t: token,
-        d: {
-          type: "Join",
-          id: gameId,
-        },
-      });
       (async () => {
-        const { host } = await simpleGet(`/game/${gameId}`);
+        const { black, white } = await simpleGet(`/game/${gameId}`);
+        // Catch up on anything said before the page was opened.
+        const chat = await simpleGet(`/game/${gameId}/chat`);
+        setMessages(chat.messages ?? []);
         const { id } = await simpleGet("/@me");
-        setColor(host === id ? Piece.Black : Piece.White);
+        // Anyone who isn't playing can only watch the game.
+        const watching = black !== id && white !== id;
+        setSpectating(watching);
+        setColor(black === id || watching ? Piece.Black : Piece.White);
+        sendJsonMessage({
+          op: watching ? 16 : 3,
+          t: token,
+          d: {
+            type: watching ? "Spectate" : "Join",
+            id: gameId,
+          },
+        });
         // This actually makes it look smoother, IMO, because the flash isn't as abrupt and disorienting.
         setTimeout(() => {
           setSetup(true);
@@ -112,26 +158,116 @@ function LiveBoard({ gameId }: LiveBoardProps) {
   const stringifyPiece = (piece: Piece) =>
     piece === Piece.Black ? "Black" : "White";
 
+  const timeLeft = (piece: Piece) => {
+    if (!clock) return null;
+    const left = piece === Piece.Black ? clock.black : clock.white;
+    const elapsed = piece === clock.turn ? now - clock.received : 0;
+    const seconds = Math.max(0, Math.ceil((left - elapsed) / 1000));
+    return `${Math.floor(seconds / 60)}:${String(seconds % 60).padStart(2, "0")}`;
+  };
+
   return (
     <main className="flex flex-col">
       <p className="mx-auto">
-        You are playing with the {stringifyPiece(color)} pieces
+        {spectating
+          ? "You are spectating this game"
+          : `You are playing with the ${stringifyPiece(color)} pieces`}
       </p>
-      <Button
-        className="border-2 border-mantle mx-auto p-2 rounded-lg mt-2 hover:border-red transition-all"
-        onClick={() =>
-          sendJsonMessage({
-            op: 4,
-            t: token,
-            d: {
-              type: "Leave",
-              id: gameId,
-            },
-          })
-        }
-      >
-        Leave Game
-      </Button>
+      {spectators > 0 && (
+        <p className="mx-auto text-sm text-subtext1">
+          {spectators} {spectators === 1 ? "spectator" : "spectators"}
+        </p>
+      )}
+      {clock && (
+        <p className="mx-auto font-mono">
+          {spectating
+            ? `Black ${timeLeft(Piece.Black)} · White ${timeLeft(Piece.White)}`
+            : `You ${timeLeft(color)} · Opponent ${timeLeft(
+                color === Piece.Black ? Piece.White : Piece.Black,
+              )}`}
+        </p>
+      )}
+      {!spectating && (
+        <>
+          <Button
+            className="border-2 border-mantle mx-auto p-2 rounded-lg mt-2 hover:border-red transition-all"
+            onClick={() =>
+              sendJsonMessage({
+                op: 4,
+                t: token,
+                d: {
+                  type: "Leave",
+                  id: gameId,
+                },
+              })
+            }
+          >
+            Leave Game
+          </Button>
+          <Button
+            className="border-2 border-mantle mx-auto p-2 rounded-lg mt-2 hover:border-mauve transition-all"
+            onClick={() =>
+              sendJsonMessage({
+                op: 8,
+                t: token,
+                d: {
+                  type: "Takeback",
+                  id: gameId,
+                },
+              })
+            }
+          >
+            Take Back
+          </Button>
+          <Button
+            className="border-2 border-mantle mx-auto p-2 rounded-lg mt-2 hover:border-mauve transition-all"
+            onClick={() =>
+              sendJsonMessage({
+                op: 11,
+                t: token,
+                d: {
+                  type: "OfferDraw",
+                  id: gameId,
+                },
+              })
+            }
+          >
+            Offer Draw
+          </Button>
+          <Button
+            className="border-2 border-mantle mx-auto p-2 rounded-lg mt-2 hover:border-red transition-all"
+            onClick={() =>
+              sendJsonMessage({
+                op: 10,
+                t: token,
+                d: {
+                  type: "Resign",
+                  id: gameId,
+                },
+              })
+            }
+          >
+            Resign
+          </Button>
+          {aborted && (
+            <Button
+              className="border-2 border-mantle mx-auto p-2 rounded-lg mt-2 hover:border-green transition-all"
+              onClick={() =>
+                sendJsonMessage({
+                  op: 14,
+                  t: token,
+                  d: {
+                    type: "Rematch",
+                    id: gameId,
+                  },
+                })
+              }
+            >
+              Rematch
+            </Button>
+          )}
+        </>
+      )}
       <div className="flex flex-row">
         <div className="mx-auto">
           <div
@@ -200,6 +336,60 @@ function LiveBoard({ gameId }: LiveBoardProps) {
           />
         </div>
       </div>
+      <section className="flex flex-col mx-auto w-[642px] mb-5">
+        <ul className="bg-crust rounded-lg p-3 h-40 overflow-y-auto text-subtext0">
+          {messages.map((message, i) => (
+            <li key={i}>
+              <span className="text-text">{message.author}</span>:{" "}
+              {message.message}
+            </li>
+          ))}
+        </ul>
+        {!spectating && (
+          <form
+            className="flex flex-row space-x-2 mt-2"
+            onSubmit={(e) => {
+              e.preventDefault();
+              if (!draft.trim()) return;
+              sendJsonMessage({
+                op: 17,
+                t: token,
+                d: {
+                  type: "Chat",
+                  id: gameId,
+                  message: draft,
+                },
+              });
+              setDraft("");
+            }}
+          >
+            <Input
+              placeholder="Say something"
+              maxLength={500}
+              value={draft}
+              className="bg-crust text-subtext0 rounded-lg p-3 flex-grow"
+              onChange={(e) => setDraft(e.currentTarget.value)}
+            ></Input>
+            <Button
+              className="border-2 border-mantle p-2 rounded-lg hover:border-red transition-all"
+              disabled={muted}
+              onClick={() => {
+                sendJsonMessage({
+                  op: 18,
+                  t: token,
+                  d: {
+                    type: "Mute",
+                    id: gameId,
+                  },
+                });
+                setMuted(true);
+              }}
+            >
+              {muted ? "Muted" : "Mute Opponent"}
+            </Button>
+          </form>
+        )}
+      </section>
     </main>
   );
 }
@@ -25,7 +25,14 @@ export default function GameList() {
                       href={`/play?gameId=${game.id}`}
                       className="text-blue hover:underline-offset-4 hover:underline hover:text-sapphire"
                     >{`Game against ${game.opponent}`}</Link>
-                    <p className="text-sm text-subtext1">{game.id}</p>
+                    {game.your_turn && (
+                      <span className="ml-2 text-sm text-green">Your turn</span>
+                    )}
+                    <p className="text-sm text-subtext1">
+                      {game.deadline
+                        ? `Move by ${new Date(game.deadline).toLocaleString()}`
+                        : game.id}
+                    </p>
                   </li>
                 );
               })}
@@ -1,7 +1,12 @@
 import toast from "react-hot-toast";
 import { BASE_API_URL, TOAST_ERROR_OPTIONS, TOAST_SUCCESS_OPTIONS } from ".";
 
-export const createGame = async (opponent: string) => {
+export type Visibility = "private" | "friends" | "public";
+
+export const createGame = async (
+  opponent: string,
+  visibility: Visibility = "private",
+) => {
   const res = await fetch(`${BASE_API_URL}/game`, {
     credentials: "include",
     method: "POST",
@@ -10,6 +15,7 @@ export const createGame = async (opponent: string) => {
     },
     body: JSON.stringify({
       guest: opponent,
+      visibility,
     }),
   });
   const { message } = await res.json();
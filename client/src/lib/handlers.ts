@@ -8,6 +8,14 @@ import {
   Context,
   GameAbortEvent,
   GameEndEvent,
+  TakebackRequestEvent,
+  TakebackDeclineEvent,
+  DrawOfferEvent,
+  DrawDeclineEvent,
+  RematchOfferEvent,
+  RematchStartEvent,
+  SpectatorCountEvent,
+  ChatMessageEvent,
 } from "@/types";
 import toast from "react-hot-toast";
 
@@ -31,30 +39,49 @@ export function handleGameAbort(context: Context<GameAbortEvent>) {
 
 export function handleGameEnd(context: Context<GameEndEvent>) {
   const { ev } = context;
+  context.setClock(undefined);
   if (!context.aborted) {
     context.setAborted(true);
+    const [black, white] = ev.d.result.score;
+    const how: Record<string, string | undefined> = {
+      resignation: " by resignation",
+      agreement: " by agreement",
+    }[ev.d.result.reason];
     toast.success(
-      `${ev.d.winner} won the game with a score of ${ev.d.points} / ${ev.d.total}!`,
+      ev.d.winner === null
+        ? `The game was drawn${how ?? ` ${black}–${white}`}!`
+        : `${ev.d.winner} won the game${how ?? ` ${black}–${white}`}!`,
       { duration: 10_000 },
     );
   }
 }
 
 export function handleGameUpdate(context: Context<GameUpdateEvent>) {
-  const { ev, board, setTurn, setPreview, setBoard } = context;
+  const { ev, setTurn, setPreview, setBoard, setClock } = context;
   const { board: gameBoard, turn } = ev.d.game;
-  for (let i = 0; i < 64; i++) {
-    const row = Math.floor(i / 8);
-    const col = i % 8;
-    const piece = gameBoard[i];
-    if (piece !== null) {
-      const color = piece === "White" ? Piece.White : Piece.Black;
-      board[row][col] = color;
-    }
-  }
+  // Boards are sent as a flat list of squares, so the width follows from its length.
+  const width = Math.sqrt(gameBoard.length);
+  const board = Array.from({ length: width }, (_, row) =>
+    Array.from({ length: width }, (_, col) => {
+      const piece = gameBoard[row * width + col];
+      if (piece === null) {
+        return null;
+      }
+      return piece === "White" ? Piece.White : Piece.Black;
+    }),
+  );
   setBoard(board);
   setTurn(turn === "White" ? Piece.White : Piece.Black);
   setPreview(undefined);
+  const { clock } = ev.d;
+  setClock(
+    clock && {
+      black: clock.black,
+      white: clock.white,
+      turn: clock.turn === "White" ? Piece.White : Piece.Black,
+      received: Date.now(),
+    },
+  );
 }
 
 export function handleErrorEvent(_: Context<ErrorEvent>) {}
@@ -63,3 +90,91 @@ export function handlePreviewEvent(context: Context<PreviewEvent>) {
   const { ev, setPreview } = context;
   setPreview(ev.d.changed);
 }
+
+export function handleTakebackRequest(context: Context<TakebackRequestEvent>) {
+  const { ev, color, token, gameId, send } = context;
+  const piece = ev.d.piece === "White" ? Piece.White : Piece.Black;
+  if (piece === color) {
+    toast("Asked your opponent to take back your last move.");
+    return;
+  }
+  const accept = window.confirm(
+    "Your opponent wants to take back their last move. Allow it?",
+  );
+  send({
+    op: 9,
+    t: token,
+    d: {
+      type: "AnswerTakeback",
+      id: gameId,
+      accept,
+    },
+  });
+}
+
+export function handleTakebackDecline(_: Context<TakebackDeclineEvent>) {
+  toast.error("The takeback was declined.");
+}
+
+export function handleDrawOffer(context: Context<DrawOfferEvent>) {
+  const { ev, color, token, gameId, send } = context;
+  const piece = ev.d.piece === "White" ? Piece.White : Piece.Black;
+  if (piece === color) {
+    toast("Offered your opponent a draw.");
+    return;
+  }
+  const accept = window.confirm("Your opponent offers a draw. Accept it?");
+  send({
+    op: accept ? 12 : 13,
+    t: token,
+    d: {
+      type: accept ? "AcceptDraw" : "DeclineDraw",
+      id: gameId,
+    },
+  });
+}
+
+export function handleDrawDecline(_: Context<DrawDeclineEvent>) {
+  toast.error("The draw offer was declined.");
+}
+
+export function handleRematchOffer(context: Context<RematchOfferEvent>) {
+  const { ev, color, token, send } = context;
+  const piece = ev.d.piece === "White" ? Piece.White : Piece.Black;
+  if (piece === color) {
+    toast("Offered your opponent a rematch.");
+    return;
+  }
+  if (window.confirm("Your opponent wants a rematch. Play again?")) {
+    send({
+      op: 15,
+      t: token,
+      d: {
+        type: "AcceptRematch",
+        id: ev.d.id,
+      },
+    });
+  }
+}
+
+export function handleRematchStart(context: Context<RematchStartEvent>) {
+  const { ev, color, setColor, setGameId, setAborted, setMessages } = context;
+  // The server has already moved this socket into the rematch, which is played with the
+  // colors swapped.
+  setGameId(ev.d.id);
+  // Each game has its own chat.
+  setMessages(() => []);
+  setColor(color === Piece.Black ? Piece.White : Piece.Black);
+  setAborted(false);
+  window.history.replaceState(null, "", `/play?gameId=${ev.d.id}`);
+  toast.success("The rematch has started!");
+}
+
+export function handleSpectatorCount(context: Context<SpectatorCountEvent>) {
+  context.setSpectators(context.ev.d.spectators);
+}
+
+export function handleChatMessage(context: Context<ChatMessageEvent>) {
+  const message = context.ev.d;
+  context.setMessages((messages) => [...messages, message]);
+}
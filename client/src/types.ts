@@ -10,6 +10,8 @@ export interface Game {
   host: string;
   opponent: string;
   ended: boolean;
+  your_turn: boolean;
+  deadline: string | null;
 }
 
 export interface Member {
@@ -31,6 +33,21 @@ export interface OutgoingFriendRequest {
 
 export type Board = Array<Array<Piece | null>>;
 
+/** A message sent in a game's chat by one of its players. */
+export interface ChatMessage {
+  author: string;
+  piece: string;
+  message: string;
+}
+
+/** The milliseconds each player had left when the clock was last sent. */
+export interface Clock {
+  black: number;
+  white: number;
+  turn: Piece;
+  received: number;
+}
+
 export interface AckEvent {
   op: 1;
 }
@@ -57,6 +74,11 @@ export interface GameUpdateEvent {
       board: Array<string | null>;
       turn: string;
     };
+    clock?: {
+      black: number;
+      white: number;
+      turn: string;
+    };
   };
 }
 
@@ -78,12 +100,64 @@ export interface ErrorEvent {
 export interface GameEndEvent {
   op: 7;
   d: {
-    winner: string;
-    points: number;
-    total: number;
+    winner: string | null;
+    result: {
+      winner: string | null;
+      score: [number, number];
+      reason: string;
+    };
+  };
+}
+
+export interface TakebackRequestEvent {
+  op: 8;
+  d: {
+    piece: string;
   };
 }
 
+export interface TakebackDeclineEvent {
+  op: 9;
+}
+
+export interface DrawOfferEvent {
+  op: 10;
+  d: {
+    piece: string;
+  };
+}
+
+export interface DrawDeclineEvent {
+  op: 11;
+}
+
+export interface RematchOfferEvent {
+  op: 12;
+  d: {
+    id: string;
+    piece: string;
+  };
+}
+
+export interface RematchStartEvent {
+  op: 13;
+  d: {
+    id: string;
+  };
+}
+
+export interface SpectatorCountEvent {
+  op: 14;
+  d: {
+    spectators: number;
+  };
+}
+
+export interface ChatMessageEvent {
+  op: 15;
+  d: ChatMessage;
+}
+
 export type Event =
   | AckEvent
   | ReadyEvent
@@ -91,7 +165,15 @@ export type Event =
   | GameUpdateEvent
   | ErrorEvent
   | PreviewEvent
-  | GameEndEvent;
+  | GameEndEvent
+  | TakebackRequestEvent
+  | TakebackDeclineEvent
+  | DrawOfferEvent
+  | DrawDeclineEvent
+  | RematchOfferEvent
+  | RematchStartEvent
+  | SpectatorCountEvent
+  | ChatMessageEvent;
 
 export interface Context<T> {
   ws: WebSocket;
@@ -99,11 +181,17 @@ export interface Context<T> {
   board: Board;
   token?: string;
   gameId: string | null;
+  color: Piece;
   aborted?: boolean;
+  send: (packet: object) => void;
   setReady: (ready: boolean) => void;
   setTurn: (turn: Piece) => void;
   setBoard: (board: Board) => void;
   setColor: (color: Piece) => void;
   setAborted: (aborted: boolean) => void;
   setPreview: (preview: Array<[number, number]> | undefined) => void;
+  setClock: (clock: Clock | undefined) => void;
+  setGameId: (gameId: string) => void;
+  setSpectators: (spectators: number) => void;
+  setMessages: (update: (messages: ChatMessage[]) => ChatMessage[]) => void;
 }
@@ -10,6 +10,16 @@ mod m20240527_185255_create_friends;
 mod m20240527_191255_create_friend_requests;
 mod m20240621_143622_invite_only_games;
 mod m20241019_164847_game_endings_and_stats;
+mod m20241102_120000_bot_opponents;
+mod m20241103_120000_game_colors;
+mod m20241104_120000_game_results;
+mod m20241105_120000_game_moves;
+mod m20241106_120000_board_width;
+mod m20241107_120000_time_controls;
+mod m20241108_120000_correspondence;
+mod m20241109_120000_rematches;
+mod m20241110_120000_game_visibility;
+mod m20241111_120000_chat_messages;
 
 pub struct Migrator;
 
@@ -26,6 +36,16 @@ impl MigratorTrait for Migrator {
             Box::new(m20240527_191255_create_friend_requests::Migration),
             Box::new(m20240621_143622_invite_only_games::Migration),
             Box::new(m20241019_164847_game_endings_and_stats::Migration),
+            Box::new(m20241102_120000_bot_opponents::Migration),
+            Box::new(m20241103_120000_game_colors::Migration),
+            Box::new(m20241104_120000_game_results::Migration),
+            Box::new(m20241105_120000_game_moves::Migration),
+            Box::new(m20241106_120000_board_width::Migration),
+            Box::new(m20241107_120000_time_controls::Migration),
+            Box::new(m20241108_120000_correspondence::Migration),
+            Box::new(m20241109_120000_rematches::Migration),
+            Box::new(m20241110_120000_game_visibility::Migration),
+            Box::new(m20241111_120000_chat_messages::Migration),
         ]
     }
 }
@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+/// The ID of the member that plays the companion's moves in games against the computer.
+const BOT_ID: &str = "00000000-0000-7000-8000-000000000001";
+const BOT_USERNAME: &str = "olly";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Game::Bot).string().null())
+                    .to_owned(),
+            )
+            .await?;
+        // A member may have registered the bot's name already, in which case the bot takes a
+        // name with its ID in it instead. The server only ever looks the bot up by its ID.
+        let taken = manager
+            .get_connection()
+            .query_one(
+                manager.get_database_backend().build(
+                    Query::select()
+                        .column(Member::Id)
+                        .from(Member::Table)
+                        .and_where(Expr::col(Member::Username).eq(BOT_USERNAME))
+                        .and_where(
+                            Expr::col(Member::Id).ne(Expr::cust(format!("'{BOT_ID}'::uuid"))),
+                        )
+                        .limit(1),
+                ),
+            )
+            .await?
+            .is_some();
+        let username = if taken {
+            format!("{BOT_USERNAME}-{BOT_ID}")
+        } else {
+            BOT_USERNAME.to_string()
+        };
+        // The bot has no password, so nobody can log in as it.
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Member::Table)
+                    .columns([Member::Id, Member::Username, Member::Password])
+                    .values_panic([
+                        Expr::cust(format!("'{BOT_ID}'::uuid")),
+                        username.into(),
+                        "".into(),
+                    ])
+                    .on_conflict(OnConflict::column(Member::Id).do_nothing().to_owned())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Member::Table)
+                    .and_where(Expr::col(Member::Id).eq(Expr::cust(format!("'{BOT_ID}'::uuid"))))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .drop_column(Game::Bot)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Bot,
+}
+
+#[derive(DeriveIden)]
+enum Member {
+    Table,
+    Id,
+    Username,
+    Password,
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Every column stays null until the game ends, and the winner stays null for draws.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Game::Winner).string().null())
+                    .add_column_if_not_exists(ColumnDef::new(Game::BlackScore).integer().null())
+                    .add_column_if_not_exists(ColumnDef::new(Game::WhiteScore).integer().null())
+                    .add_column_if_not_exists(ColumnDef::new(Game::Reason).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .drop_column(Game::Winner)
+                    .drop_column(Game::BlackScore)
+                    .drop_column(Game::WhiteScore)
+                    .drop_column(Game::Reason)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Winner,
+    BlackScore,
+    WhiteScore,
+    Reason,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Passes aren't stored, so they show up as gaps between plies.
+        manager
+            .create_table(
+                Table::create()
+                    .table(GameMove::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(GameMove::GameId).uuid().not_null())
+                    .col(ColumnDef::new(GameMove::Ply).integer().not_null())
+                    .col(ColumnDef::new(GameMove::X).integer().not_null())
+                    .col(ColumnDef::new(GameMove::Y).integer().not_null())
+                    .col(ColumnDef::new(GameMove::Color).string().not_null())
+                    .col(ColumnDef::new(GameMove::Flips).integer().not_null())
+                    .col(
+                        ColumnDef::new(GameMove::PlayedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(GameMove::Table, GameMove::GameId)
+                            .to(Game::Table, Game::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .table(GameMove::Table)
+                            .col(GameMove::GameId)
+                            .col(GameMove::Ply),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GameMove::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameMove {
+    Table,
+    GameId,
+    Ply,
+    X,
+    Y,
+    Color,
+    Flips,
+    PlayedAt,
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Id,
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Games without a time control leave every column null, as existing games do.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Game::TimeBase).integer().null())
+                    .add_column_if_not_exists(ColumnDef::new(Game::TimeIncrement).integer().null())
+                    .add_column_if_not_exists(ColumnDef::new(Game::TimePerMove).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .drop_column(Game::TimeBase)
+                    .drop_column(Game::TimeIncrement)
+                    .drop_column(Game::TimePerMove)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    TimeBase,
+    TimeIncrement,
+    TimePerMove,
+}
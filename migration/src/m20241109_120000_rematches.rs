@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A rematch outlives the game it was a rematch of, in case that game is deleted.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Game::RematchOf).uuid().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_game_rematch_of")
+                            .from_tbl(Game::Table)
+                            .from_col(Game::RematchOf)
+                            .to_tbl(Game::Table)
+                            .to_col(Game::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Game::Table)
+                    .drop_foreign_key(Alias::new("fk_game_rematch_of"))
+                    .drop_column(Game::RematchOf)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Id,
+    RematchOf,
+}
@@ -1,6 +1,12 @@
 use std::sync::Arc;
 
-use olly::server::{app, restore_active_games, AppState, DEFAULT_DATABASE_URI, DEFAULT_REDIS_URI};
+use olly::{
+    server::{
+        app, restore_active_games, schedule_deadlines, AppState, DEFAULT_DATABASE_URI,
+        DEFAULT_REDIS_URI,
+    },
+    Book,
+};
 use sea_orm::Database;
 use tokio::net::TcpListener;
 
@@ -17,9 +23,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let redis = redis::Client::open(redis_url).unwrap();
     // Ensure the connection to the database is established.
     let _ = redis.get_connection().unwrap();
-    let state = Arc::new(AppState::new(database, redis));
+    // Use the opening book at BOOK_PATH if one is given, or the bundled book otherwise.
+    let book = match std::env::var("BOOK_PATH") {
+        Ok(path) => std::fs::read_to_string(path)?.parse()?,
+        Err(_) => Book::openings(),
+    };
+    let state = Arc::new(AppState::new(database, redis).with_book(book));
     // Restore any active games to the cache.
     restore_active_games(&state).await?;
+    // Forfeit correspondence games once a player misses their deadline.
+    schedule_deadlines(&state);
     let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
     // Serve the app on the port specified above.
     axum::serve(listener, app(state)).await.unwrap();
@@ -1,9 +1,64 @@
-use serde::{Deserialize, Serialize};
+use crate::{notation, PositionError};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     fmt,
-    ops::{Index, IndexMut, Not},
+    ops::{Index, Not},
 };
 
+/// A set of squares with one bit per square, where square (x, y) is bit `x + y * width`.
+pub type Bitboard = u128;
+
+/// The widths of the boards that games can be played on. A standard Othello board is 8x8.
+pub const WIDTHS: [usize; 3] = [6, 8, 10];
+
+/// The number of squares on the largest supported board.
+pub(crate) const MAX_SQUARES: usize = 100;
+
+/// The squares along each edge of a board, and every square on it.
+#[derive(Copy, Clone)]
+struct Masks {
+    all: Bitboard,
+    left: Bitboard,
+    right: Bitboard,
+    top: Bitboard,
+    bottom: Bitboard,
+}
+
+/// The masks for each width up to the largest supported one, indexed by width.
+const MASKS: [Masks; 11] = masks();
+
+/// Computes the edge masks at compile time.
+const fn masks() -> [Masks; 11] {
+    let empty = Masks {
+        all: 0,
+        left: 0,
+        right: 0,
+        top: 0,
+        bottom: 0,
+    };
+    let mut masks = [empty; 11];
+    let mut width = 1;
+    while width < masks.len() {
+        let top = (1 << width) - 1;
+        let mut left = 0;
+        let mut y = 0;
+        while y < width {
+            left |= 1 << (y * width);
+            y += 1;
+        }
+        masks[width] = Masks {
+            all: (1 << (width * width)) - 1,
+            left,
+            right: left << (width - 1),
+            top,
+            bottom: top << (width * (width - 1)),
+        };
+        width += 1;
+    }
+    masks
+}
+
+/// The eight directions a line of flips can run in, as `(dx, dy)` offsets.
 const DIRECTIONS: &[(i8, i8)] = &[
     (-1, -1), // Left diagonal
     (0, -1),  // Top
@@ -15,7 +70,7 @@ const DIRECTIONS: &[(i8, i8)] = &[
     (1, 1),   // Bottom right
 ];
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Piece {
     Black,
     White,
@@ -32,11 +87,17 @@ impl Not for Piece {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub(super) struct Board(Vec<Option<Piece>>);
+/// An Othello board stored as one bitboard per color. Square (x, y) is bit `x + y * width`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Board {
+    black: Bitboard,
+    white: Bitboard,
+    width: usize,
+}
 
 impl Board {
-    /// Initializes an Othello board with the standard starting state.
+    /// Initializes an 8x8 Othello board with the standard starting state.
+    #[must_use]
     pub fn new() -> Self {
         /*
         ........
@@ -48,84 +109,314 @@ impl Board {
         ........
         ........
         */
-        let mut board = Self(vec![None; Self::width() * Self::width()]);
-        board[(3, 3)] = Some(Piece::White); // Top left
-        board[(4, 3)] = Some(Piece::Black); // Top right
-        board[(3, 4)] = Some(Piece::Black); // Bottom left
-        board[(4, 4)] = Some(Piece::White); // Bottom right
+        Self::with_width(8)
+    }
+
+    /// Initializes a `width` by `width` board with the four center squares filled in the
+    /// same pattern as a standard board.
+    ///
+    /// # Panics
+    /// Panics if `width` isn't one of [`WIDTHS`].
+    #[must_use]
+    pub fn with_width(width: usize) -> Self {
+        assert!(
+            WIDTHS.contains(&width),
+            "{width}x{width} boards are not supported"
+        );
+        let mut board = Self {
+            black: 0,
+            white: 0,
+            width,
+        };
+        let (low, high) = (width / 2 - 1, width / 2);
+        board.set(low, low, Some(Piece::White)); // Top left
+        board.set(high, low, Some(Piece::Black)); // Top right
+        board.set(low, high, Some(Piece::Black)); // Bottom left
+        board.set(high, high, Some(Piece::White)); // Bottom right
         board
     }
 
+    /// Parses a position written as one character per square, row by row from the top left,
+    /// with `X` for black, `O` for white and `-` for an empty square. The width of the board
+    /// follows from the number of squares. Whitespace is ignored, so the rows can be written
+    /// on separate lines.
+    ///
+    /// # Errors
+    /// Returns an error if the squares don't make up a supported board, one of them is some
+    /// other character, or one of the four center squares is empty, which can't happen in a
+    /// game of Othello.
+    pub fn from_position(text: &str) -> Result<Self, PositionError> {
+        let squares: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let Some(&width) = WIDTHS.iter().find(|&&width| width * width == squares.len()) else {
+            return Err(PositionError::Length(squares.len()));
+        };
+        let mut board = Self {
+            black: 0,
+            white: 0,
+            width,
+        };
+        for (i, &c) in squares.iter().enumerate() {
+            let (x, y) = (i % width, i / width);
+            let piece = match c {
+                'X' | 'x' => Some(Piece::Black),
+                'O' | 'o' => Some(Piece::White),
+                '-' => None,
+                _ => return Err(PositionError::Square(notation::name((x, y)), c)),
+            };
+            board.set(x, y, piece);
+        }
+        let center = [width / 2 - 1, width / 2];
+        if center
+            .map(|x| center.map(|y| board.bit(x, y)))
+            .into_iter()
+            .flatten()
+            .any(|bit| board.empty() & bit != 0)
+        {
+            return Err(PositionError::Center);
+        }
+        Ok(board)
+    }
+
+    /// The position on the board as one character per square, the inverse of
+    /// [`Board::from_position`].
+    #[must_use]
+    pub fn position(&self) -> String {
+        let width = self.width;
+        (0..width * width)
+            .map(|i| match self[(i % width, i / width)] {
+                Some(Piece::Black) => 'X',
+                Some(Piece::White) => 'O',
+                None => '-',
+            })
+            .collect()
+    }
+
+    /// The bitboard of squares occupied by `piece`.
+    #[must_use]
+    pub fn pieces(&self, piece: Piece) -> Bitboard {
+        match piece {
+            Piece::Black => self.black,
+            Piece::White => self.white,
+        }
+    }
+
+    /// The number of squares occupied by `piece`.
+    #[must_use]
+    pub fn count(&self, piece: Piece) -> usize {
+        self.pieces(piece).count_ones() as usize
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, piece: Option<Piece>) {
+        let bit = self.bit(x, y);
+        self.black &= !bit;
+        self.white &= !bit;
+        match piece {
+            Some(Piece::Black) => self.black |= bit,
+            Some(Piece::White) => self.white |= bit,
+            None => (),
+        }
+    }
+
+    /// # Panics
+    /// Panics if (x, y) is out of bounds.
+    #[must_use]
     pub fn adjacent(&self, x: usize, y: usize) -> bool {
         // Calling code ensures that x and y are within bounds.
-        assert!(x < Self::width() && y < Self::width());
-        let (x, y): (i8, i8) = crate::convert(x, y);
+        assert!(x < self.width && y < self.width);
+        let occupied = self.black | self.white;
         DIRECTIONS
             .iter()
-            .filter(|(dx, dy)| x + dx >= 0 && y + dy >= 0)
-            .map(|(dx, dy)| (x + dx, y + dy))
-            .map(|(x, y)| crate::convert(x, y))
-            .filter(|&(x, y)| x < Self::width() && y < Self::width())
-            .any(|(x, y)| self[(x, y)].is_some())
+            .any(|&direction| self.shift(occupied, direction) & self.bit(x, y) != 0)
     }
 
-    pub fn flip(&mut self, x: usize, y: usize, piece: Piece, update: bool) -> Vec<(usize, usize)> {
+    /// The bitboard of squares where `piece` may legally be placed.
+    #[must_use]
+    pub fn moves(&self, piece: Piece) -> Bitboard {
+        let own = self.pieces(piece);
+        let opponent = self.pieces(!piece);
+        let empty = self.empty();
+        DIRECTIONS.iter().fold(0, |moves, &direction| {
+            // Walk runs of opponent pieces outward from our own pieces. There can be
+            // at most `width - 2` of them in a row before the edge of the board.
+            let mut run = self.shift(own, direction) & opponent;
+            for _ in 0..self.width - 3 {
+                run |= self.shift(run, direction) & opponent;
+            }
+            moves | self.shift(run, direction) & empty
+        })
+    }
+
+    /// The bitboard of pieces that would be flipped by placing `piece` at (x, y).
+    /// # Panics
+    /// Panics if (x, y) is out of bounds.
+    #[must_use]
+    pub fn flips(&self, x: usize, y: usize, piece: Piece) -> Bitboard {
         // Calling code ensures that x and y are within bounds.
-        assert!(x < Self::width() && y < Self::width());
-        let mut flips = vec![];
-        let (x, y) = crate::convert(x, y);
-        for (dx, dy) in DIRECTIONS {
-            if self.on((x, y), (dx, dy), piece) {
-                let opponent = !piece;
-                let mut x = x + dx;
-                let mut y = y + dy;
-                while Self::within_bounds(x, y) {
-                    let cur = crate::convert(x, y);
-                    match self[cur] {
-                        Some(p) if p == opponent => {
-                            let piece = if update { Some(piece) } else { self[cur] };
-                            self[cur] = {
-                                flips.push(cur);
-                                piece
-                            };
-                            x += dx;
-                            y += dy;
-                        }
-                        Some(p) if p == piece => break,
-                        _ => break,
-                    }
-                }
+        assert!(x < self.width && y < self.width);
+        let own = self.pieces(piece);
+        let opponent = self.pieces(!piece);
+        DIRECTIONS.iter().fold(0, |flips, &direction| {
+            let mut line = 0;
+            let mut cur = self.shift(self.bit(x, y), direction);
+            while cur & opponent != 0 {
+                line |= cur;
+                cur = self.shift(cur, direction);
+            }
+            if cur & own == 0 {
+                flips
+            } else {
+                flips | line
+            }
+        })
+    }
+
+    /// Places `piece` at (x, y) and flips the captured pieces, returning them as a bitboard.
+    pub fn place(&mut self, x: usize, y: usize, piece: Piece) -> Bitboard {
+        let flips = self.flips(x, y, piece);
+        let placed = self.bit(x, y) | flips;
+        match piece {
+            Piece::Black => {
+                self.black |= placed;
+                self.white &= !placed;
+            }
+            Piece::White => {
+                self.white |= placed;
+                self.black &= !placed;
             }
         }
         flips
     }
 
-    fn on(&self, (x, y): (i8, i8), (dx, dy): (&i8, &i8), piece: Piece) -> bool {
-        let mut x = x + dx;
-        let mut y = y + dy;
-        while Self::within_bounds(x, y) {
-            let cur = crate::convert(x, y);
-            match self[cur] {
-                Some(p) if p == !piece => {
-                    x += dx;
-                    y += dy;
-                }
-                Some(p) if p == piece => return true,
-                _ => break,
+    /// Lists the (x, y) coordinates of the squares set in `bits`.
+    #[must_use]
+    pub fn squares(&self, bits: Bitboard) -> Vec<(usize, usize)> {
+        let mut squares = vec![];
+        let mut bits = bits;
+        while bits != 0 {
+            let i = bits.trailing_zeros() as usize;
+            squares.push((i % self.width, i / self.width));
+            bits &= bits - 1;
+        }
+        squares
+    }
+
+    /// The bitboard with only (x, y) set.
+    #[must_use]
+    pub fn bit(&self, x: usize, y: usize) -> Bitboard {
+        1 << (x + y * self.width)
+    }
+
+    /// The bitboard of empty squares.
+    #[must_use]
+    pub fn empty(&self) -> Bitboard {
+        !(self.black | self.white) & self.masks().all
+    }
+
+    /// The bitboard of the four corners.
+    #[must_use]
+    pub fn corners(&self) -> Bitboard {
+        let masks = self.masks();
+        (masks.left | masks.right) & (masks.top | masks.bottom)
+    }
+
+    /// The bitboard of squares along the edges of the board, including the corners.
+    #[must_use]
+    pub fn edges(&self) -> Bitboard {
+        let masks = self.masks();
+        masks.left | masks.right | masks.top | masks.bottom
+    }
+
+    /// The bitboard of squares adjacent to any square set in `bits`, not including `bits`.
+    #[must_use]
+    pub fn neighbours(&self, bits: Bitboard) -> Bitboard {
+        DIRECTIONS.iter().fold(0, |neighbours, &direction| {
+            neighbours | self.shift(bits, direction)
+        }) & !bits
+    }
+
+    /// The bitboard of `piece`'s discs that can never be flipped for the rest of the game.
+    ///
+    /// A disc is stable when, along each of the four lines through it, either the line is
+    /// full or one of its neighbours on that line is the edge of the board or another stable
+    /// disc of the same color.
+    #[must_use]
+    pub fn stable(&self, piece: Piece) -> Bitboard {
+        let own = self.pieces(piece);
+        let occupied = own | self.pieces(!piece);
+        // The first four directions each run along a different line; the other four are
+        // their opposites.
+        let full: Vec<Bitboard> = DIRECTIONS[..4]
+            .iter()
+            .map(|&direction| {
+                self.filled(occupied, direction) & self.filled(occupied, Self::reverse(direction))
+            })
+            .collect();
+        let mut stable = 0;
+        loop {
+            let anchored =
+                |direction| self.edge(direction) | self.shift(stable, Self::reverse(direction));
+            let next = DIRECTIONS[..4]
+                .iter()
+                .zip(&full)
+                .fold(own, |next, (&direction, full)| {
+                    next & (full | anchored(direction) | anchored(Self::reverse(direction)))
+                });
+            if next == stable {
+                return stable;
             }
+            stable = next;
+        }
+    }
+
+    /// The bitboard of occupied squares from which every square in `direction`, up to the
+    /// edge of the board, is also occupied.
+    fn filled(&self, occupied: Bitboard, direction: (i8, i8)) -> Bitboard {
+        let mut filled = occupied & self.edge(direction);
+        for _ in 0..self.width {
+            filled |= occupied & self.shift(filled, Self::reverse(direction));
+        }
+        filled
+    }
+
+    /// The bitboard of squares whose neighbour in `direction` is off of the board.
+    fn edge(&self, direction: (i8, i8)) -> Bitboard {
+        let all = self.masks().all;
+        !self.shift(all, Self::reverse(direction)) & all
+    }
+
+    fn reverse((dx, dy): (i8, i8)) -> (i8, i8) {
+        (-dx, -dy)
+    }
+
+    /// Moves every set square one step in `direction`, dropping squares that leave the board.
+    fn shift(&self, bits: Bitboard, (dx, dy): (i8, i8)) -> Bitboard {
+        let masks = self.masks();
+        let bits = match dx {
+            1 => (bits & !masks.right) << 1,
+            -1 => (bits & !masks.left) >> 1,
+            _ => bits,
+        };
+        match dy {
+            1 => (bits << self.width) & masks.all,
+            -1 => bits >> self.width,
+            _ => bits,
         }
-        false
     }
 
-    #[allow(clippy::cast_possible_truncation)] // 8 <= i8::MAX
-    fn within_bounds(x: i8, y: i8) -> bool {
-        const WIDTH: i8 = Board::width() as i8;
-        x >= 0 && y >= 0 && x < WIDTH && y < WIDTH
+    fn masks(&self) -> &'static Masks {
+        &MASKS[self.width]
+    }
+
+    /// The width of the board, which is one of [`WIDTHS`].
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
     }
+}
 
-    /// The width of the board. A standard Othello board is an 8x8 grid.
-    pub const fn width() -> usize {
-        8
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -133,30 +424,59 @@ impl Index<(usize, usize)> for Board {
     type Output = Option<Piece>;
 
     fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
-        let i = x + y * Self::width();
-        &self.0[i]
+        let bit = self.bit(x, y);
+        if self.black & bit != 0 {
+            &Some(Piece::Black)
+        } else if self.white & bit != 0 {
+            &Some(Piece::White)
+        } else {
+            &None
+        }
     }
 }
 
-impl IndexMut<(usize, usize)> for Board {
-    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
-        let i = x + y * Self::width();
-        &mut self.0[i]
+// Boards are (de)serialized as a flat list of squares, indexed by `x + y * width`. The width
+// of the board follows from the length of the list.
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let width = self.width;
+        serializer.collect_seq((0..width * width).map(|i| self[(i % width, i / width)]))
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let squares = Vec::<Option<Piece>>::deserialize(deserializer)?;
+        let Some(&width) = WIDTHS.iter().find(|&&width| width * width == squares.len()) else {
+            return Err(de::Error::invalid_length(
+                squares.len(),
+                &"36, 64 or 100 board squares",
+            ));
+        };
+        let mut board = Self {
+            black: 0,
+            white: 0,
+            width,
+        };
+        for (i, piece) in squares.into_iter().enumerate() {
+            board.set(i % width, i / width, piece);
+        }
+        Ok(board)
     }
 }
 
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (i, piece) in self.0.iter().enumerate() {
-            let c = match piece {
-                Some(Piece::Black) => '○',
-                Some(Piece::White) => '●',
-                None => '.',
-            };
-            write!(f, "{c}")?;
-            if i % 8 == 7 {
-                writeln!(f)?;
+        for y in 0..self.width {
+            for x in 0..self.width {
+                let c = match self[(x, y)] {
+                    Some(Piece::Black) => '○',
+                    Some(Piece::White) => '●',
+                    None => '.',
+                };
+                write!(f, "{c}")?;
             }
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -164,7 +484,33 @@ impl fmt::Debug for Board {
 
 #[cfg(test)]
 mod tests {
-    use super::Board;
+    use super::{Board, Piece, WIDTHS};
+    use crate::PositionError;
+
+    /// Counts the leaf nodes of the game tree `depth` plies below `board`. A forced
+    /// pass counts as a ply, and a finished game is a single leaf.
+    fn perft(board: Board, piece: Piece, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = board.moves(piece);
+        if moves == 0 {
+            return if board.moves(!piece) == 0 {
+                1
+            } else {
+                perft(board, !piece, depth - 1)
+            };
+        }
+        board
+            .squares(moves)
+            .into_iter()
+            .map(|(x, y)| {
+                let mut child = board;
+                child.place(x, y, piece);
+                perft(child, !piece, depth - 1)
+            })
+            .sum()
+    }
 
     #[test]
     fn adjacent() {
@@ -172,4 +518,136 @@ mod tests {
         assert!(!board.adjacent(0, 0));
         assert!(board.adjacent(2, 3));
     }
+
+    #[test]
+    fn initial_moves() {
+        let board = Board::new();
+        let moves = board.squares(board.moves(Piece::Black));
+        assert_eq!(moves, vec![(3, 2), (2, 3), (5, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn place() {
+        let mut board = Board::new();
+        let flips = board.place(2, 3, Piece::Black);
+        assert_eq!(board.squares(flips), vec![(3, 3)]);
+        assert_eq!(board.count(Piece::Black), 4);
+        assert_eq!(board.count(Piece::White), 1);
+    }
+
+    #[test]
+    fn edges() {
+        // A white run along the top edge must not wrap around to the next row.
+        for width in WIDTHS {
+            let mut board = Board::with_width(width);
+            for x in 0..width {
+                for y in 0..width {
+                    board.set(x, y, None);
+                }
+            }
+            board.set(width - 1, 0, Some(Piece::White));
+            board.set(0, 1, Some(Piece::Black));
+            assert_eq!(board.moves(Piece::Black), 0);
+            assert_eq!(board.flips(width - 2, 0, Piece::Black), 0);
+        }
+    }
+
+    #[test]
+    fn stable() {
+        let mut board = Board::new();
+        assert_eq!(board.stable(Piece::Black), 0);
+        // A corner disc is always stable, and so is a disc anchored to it along the edge.
+        board.set(0, 0, Some(Piece::Black));
+        board.set(1, 0, Some(Piece::Black));
+        board.set(1, 1, Some(Piece::Black));
+        let stable = board.stable(Piece::Black);
+        assert_eq!(board.squares(stable), vec![(0, 0), (1, 0)]);
+        // Every disc on a full board is stable.
+        for width in WIDTHS {
+            let half = width * width / 2;
+            let full = Board::from_position(&("O".repeat(half) + &"X".repeat(half))).unwrap();
+            assert_eq!(
+                full.stable(Piece::Black) | full.stable(Piece::White),
+                full.pieces(Piece::Black) | full.pieces(Piece::White)
+            );
+            assert_eq!(full.width(), width);
+            assert_eq!(full.empty(), 0);
+        }
+    }
+
+    #[test]
+    fn serde() {
+        let board = Board::new();
+        let json = serde_json::to_string(&board).unwrap();
+        let squares: Vec<Option<Piece>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(squares.len(), 64);
+        assert_eq!(squares[3 + 3 * 8], Some(Piece::White));
+        assert_eq!(squares[4 + 3 * 8], Some(Piece::Black));
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+        assert!(serde_json::from_str::<Board>("[null]").is_err());
+        let small = Board::with_width(6);
+        let json = serde_json::to_string(&small).unwrap();
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), small);
+    }
+
+    #[test]
+    fn widths() {
+        for width in WIDTHS {
+            let board = Board::with_width(width);
+            let center = width / 2;
+            assert_eq!(board[(center - 1, center - 1)], Some(Piece::White));
+            assert_eq!(board[(center, center - 1)], Some(Piece::Black));
+            assert_eq!(
+                board.squares(board.moves(Piece::Black)),
+                vec![
+                    (center - 1, center - 2),
+                    (center - 2, center - 1),
+                    (center + 1, center),
+                    (center, center + 1)
+                ]
+            );
+            assert_eq!(board.squares(board.corners()).len(), 4);
+            assert_eq!(board.squares(board.edges()).len(), 4 * (width - 1));
+            assert_eq!(Board::from_position(&board.position()).unwrap(), board);
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let position = Board::new().position();
+        assert_eq!(
+            position,
+            "---------------------------OX------XO---------------------------"
+        );
+        assert_eq!(Board::from_position(&position).unwrap(), Board::new());
+        // Rows can be on separate lines, and letters in either case.
+        let rows = "--------\n--------\n--------\n---ox---\n---xo---\n--------\n--------\n--------";
+        assert_eq!(Board::from_position(rows).unwrap(), Board::new());
+        assert_eq!(
+            Board::from_position(&position[1..]).unwrap_err(),
+            PositionError::Length(63)
+        );
+        assert_eq!(
+            Board::from_position(&position.replacen('-', "?", 1)).unwrap_err(),
+            PositionError::Square("a1".to_string(), '?')
+        );
+        assert_eq!(
+            Board::from_position(&position.replacen('O', "-", 1)).unwrap_err(),
+            PositionError::Center
+        );
+    }
+
+    #[test]
+    fn perft_shallow() {
+        let expected = [1, 4, 12, 56, 244, 1396, 8200];
+        for (depth, &nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(Board::new(), Piece::Black, depth), nodes);
+        }
+    }
+
+    #[test]
+    fn perft_deep() {
+        assert_eq!(perft(Board::new(), Piece::Black, 7), 55_092);
+        assert_eq!(perft(Board::new(), Piece::Black, 8), 390_216);
+    }
 }
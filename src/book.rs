@@ -0,0 +1,283 @@
+use crate::{notation, Bitboard, Board, Game, NotationError, Piece, PlaceError};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use std::{collections::HashMap, str::FromStr};
+
+/// The opening book bundled with the companion.
+const OPENINGS: &str = include_str!("../data/openings.txt");
+
+/// The width of the board that books are written for.
+const WIDTH: usize = 8;
+
+/// The moves played from a position in the book, each with its total weight.
+type Continuations = Vec<((usize, usize), u32)>;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum BookError {
+    #[error("line {0}: {1:?} is not a square in coordinate notation")]
+    Square(usize, String),
+    #[error("line {0}: {1:?} is not a valid weight")]
+    Weight(usize, String),
+    #[error("line {0}: {1}")]
+    Move(usize, PlaceError),
+}
+
+/// A collection of well-studied opening lines for the companion to play from instead of
+/// searching.
+///
+/// Books are written as one line per opening, each a sequence of moves in coordinate notation
+/// (such as `f5d6c3d3c4`) optionally followed by a weight. Blank lines and anything after a
+/// `#` are ignored. When several lines continue the same position differently, each
+/// continuation is picked in proportion to the total weight of the lines that play it.
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    /// The weighted continuations of each position in the book, keyed by the canonical form
+    /// of the position and the player to move. Moves are stored in canonical coordinates too.
+    positions: HashMap<((Bitboard, Bitboard), Piece), Continuations>,
+    /// The length of the longest line in the book, past which no position can be found.
+    depth: usize,
+}
+
+impl Book {
+    /// The opening book bundled with the companion.
+    ///
+    /// # Panics
+    /// Panics if the bundled book is malformed, which its tests rule out.
+    #[must_use]
+    pub fn openings() -> Self {
+        OPENINGS.parse().expect("the bundled opening book is valid")
+    }
+
+    /// The book moves for the player to move in `game`, with their weights. Positions that
+    /// are a rotation or reflection of a book position are found too. Books only cover games
+    /// on a standard board.
+    #[must_use]
+    pub fn moves(&self, game: &Game) -> Vec<((usize, usize), u32)> {
+        if game.width() != WIDTH || game.history().len() > self.depth {
+            return vec![];
+        }
+        let (key, symmetries) = canonical(game.board());
+        self.positions
+            .get(&(key, game.turn()))
+            .map(|moves| {
+                moves
+                    .iter()
+                    .map(|&(square, weight)| (symmetries[0].invert(square), weight))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Picks one of the book moves for `game` at random, in proportion to their weights.
+    /// Returns `None` once the game has left the book.
+    pub fn choose<R: Rng + ?Sized>(&self, game: &Game, rng: &mut R) -> Option<(usize, usize)> {
+        let moves = self.moves(game);
+        let weights = WeightedIndex::new(moves.iter().map(|&(_, weight)| weight)).ok()?;
+        Some(moves[weights.sample(rng)].0)
+    }
+
+    fn insert(&mut self, line: &[(usize, usize)], weight: u32) -> Result<(), PlaceError> {
+        let mut game = Game::new();
+        for &(x, y) in line {
+            let (key, symmetries) = canonical(game.board());
+            // When the position is symmetric, equivalent moves are stored as the same move.
+            let canonical = symmetries
+                .iter()
+                .map(|symmetry| symmetry.apply((x, y)))
+                .min()
+                .unwrap_or((x, y));
+            let moves = self.positions.entry((key, game.turn())).or_default();
+            match moves.iter_mut().find(|(square, _)| *square == canonical) {
+                Some((_, total)) => *total += weight,
+                None => moves.push((canonical, weight)),
+            }
+            game.place(x, y, game.turn())?;
+        }
+        self.depth = self.depth.max(game.history().len());
+        Ok(())
+    }
+}
+
+impl FromStr for Book {
+    type Err = BookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut book = Self::default();
+        for (i, line) in s.lines().enumerate() {
+            let number = i + 1;
+            let line = line.split('#').next().unwrap_or_default();
+            let mut parts = line.split_whitespace();
+            let Some(moves) = parts.next() else {
+                continue;
+            };
+            let weight = match parts.next() {
+                Some(weight) => weight
+                    .parse()
+                    .map_err(|_| BookError::Weight(number, weight.to_string()))?,
+                None => 1,
+            };
+            let squares = notation::squares(moves).map_err(|e| match e {
+                NotationError::Square(text) => BookError::Square(number, text),
+                NotationError::Move(_, e) => BookError::Move(number, e),
+            })?;
+            book.insert(&squares, weight)
+                .map_err(|e| BookError::Move(number, e))?;
+        }
+        Ok(book)
+    }
+}
+
+/// The canonical form of `board`, which is the same for every rotation and reflection of
+/// it, along with the symmetries that transform `board` into that form.
+fn canonical(board: &Board) -> ((Bitboard, Bitboard), Vec<Symmetry>) {
+    let key = |board: Board| (board.pieces(Piece::Black), board.pieces(Piece::White));
+    let forms: Vec<_> = SYMMETRIES
+        .iter()
+        .map(|&symmetry| (key(symmetry.board(board)), symmetry))
+        .collect();
+    let min = forms
+        .iter()
+        .map(|&(form, _)| form)
+        .min()
+        .unwrap_or_default();
+    let symmetries = forms
+        .into_iter()
+        .filter(|&(form, _)| form == min)
+        .map(|(_, symmetry)| symmetry)
+        .collect();
+    (min, symmetries)
+}
+
+/// The eight ways to rotate or reflect the board onto itself. The rules of Othello don't
+/// change under any of them, so neither does the value of a move.
+const SYMMETRIES: [Symmetry; 8] = [
+    Symmetry(false, false, false),
+    Symmetry(false, false, true),
+    Symmetry(false, true, false),
+    Symmetry(false, true, true),
+    Symmetry(true, false, false),
+    Symmetry(true, false, true),
+    Symmetry(true, true, false),
+    Symmetry(true, true, true),
+];
+
+/// Transposes the board, then optionally mirrors it left to right and top to bottom.
+#[derive(Debug, Clone, Copy)]
+struct Symmetry(bool, bool, bool);
+
+impl Symmetry {
+    fn apply(self, (x, y): (usize, usize)) -> (usize, usize) {
+        let last = WIDTH - 1;
+        let Self(transpose, mirror, flip) = self;
+        let (x, y) = if transpose { (y, x) } else { (x, y) };
+        (
+            if mirror { last - x } else { x },
+            if flip { last - y } else { y },
+        )
+    }
+
+    fn invert(self, (x, y): (usize, usize)) -> (usize, usize) {
+        let last = WIDTH - 1;
+        let Self(transpose, mirror, flip) = self;
+        let (x, y) = (
+            if mirror { last - x } else { x },
+            if flip { last - y } else { y },
+        );
+        if transpose {
+            (y, x)
+        } else {
+            (x, y)
+        }
+    }
+
+    fn board(self, board: &Board) -> Board {
+        let mut transformed = *board;
+        for x in 0..WIDTH {
+            for y in 0..WIDTH {
+                let (tx, ty) = self.apply((x, y));
+                transformed.set(tx, ty, board[(x, y)]);
+            }
+        }
+        transformed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Book, BookError, SYMMETRIES};
+    use crate::{Game, PlaceError};
+
+    #[test]
+    fn symmetries() {
+        for symmetry in SYMMETRIES {
+            for square in [(0, 0), (5, 4), (2, 7)] {
+                assert_eq!(symmetry.invert(symmetry.apply(square)), square);
+            }
+        }
+    }
+
+    #[test]
+    fn weights() {
+        let book: Book = "f5d6 3\nf5f6 # The diagonal opening\n\nf5f4"
+            .parse()
+            .unwrap();
+        let mut game = Game::new();
+        // The lines all start the same way, so their weights add up.
+        let moves = book.moves(&game);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].1, 5);
+        assert!(game.moves(game.turn()).contains(&moves[0].0));
+        game.place(5, 4, game.turn()).unwrap();
+        assert_eq!(
+            book.moves(&game),
+            vec![((3, 5), 3), ((5, 5), 1), ((5, 3), 1)]
+        );
+        game.place(5, 3, game.turn()).unwrap();
+        assert_eq!(book.moves(&game), vec![]);
+        assert_eq!(book.choose(&game, &mut rand::thread_rng()), None);
+    }
+
+    #[test]
+    fn symmetric_positions() {
+        let book: Book = "f5d6c3".parse().unwrap();
+        // Each opening move leads to the same position, up to symmetry.
+        for (x, y) in Game::new().moves(crate::Piece::Black) {
+            let mut game = Game::new();
+            game.place(x, y, game.turn()).unwrap();
+            let moves = book.moves(&game);
+            assert_eq!(moves.len(), 1);
+            let (reply, _) = moves[0];
+            game.place(reply.0, reply.1, game.turn()).unwrap();
+            assert_eq!(book.moves(&game).len(), 1);
+        }
+        let mut game = Game::new();
+        game.place(3, 2, game.turn()).unwrap();
+        assert_eq!(book.moves(&game), vec![((2, 4), 1)]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "f5d6\nf5z9".parse::<Book>().unwrap_err(),
+            BookError::Square(2, "z9".to_string())
+        );
+        assert_eq!(
+            "f5d6 heavy".parse::<Book>().unwrap_err(),
+            BookError::Weight(1, "heavy".to_string())
+        );
+        assert_eq!(
+            "f5f5".parse::<Book>().unwrap_err(),
+            BookError::Move(1, PlaceError::Occupied(5, 4))
+        );
+    }
+
+    #[test]
+    fn openings() {
+        let book = Book::openings();
+        let game = Game::new();
+        let mut rng = rand::thread_rng();
+        for _ in 0..10 {
+            let square = book.choose(&game, &mut rng).unwrap();
+            assert!(game.moves(game.turn()).contains(&square));
+        }
+    }
+}
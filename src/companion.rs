@@ -1,82 +1,934 @@
-use crate::{Game, Piece};
+use crate::{
+    board::MAX_SQUARES,
+    book::Book,
+    difficulty::Settings,
+    evaluation::{DiscCount, Evaluator, Positional},
+    transposition::{zobrist, Bound, Entry, TranspositionTable},
+    Bitboard, Board, Game, Piece,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
 
-pub struct Companion<'a> {
+/// A score larger than any heuristic value, used as the initial search window.
+const INFINITY: isize = isize::MAX / 2;
+
+/// How many nodes to search between checks of the time budget.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// The number of empty squares at or below which the companion solves the game exactly
+/// instead of searching with its evaluator.
+pub const ENDGAME_EMPTIES: usize = 14;
+
+/// How much of the endgame the solver should prove.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solve {
+    /// Find the exact final disc differential.
+    Exact,
+    /// Only find whether the game is a win, loss or draw, which is faster.
+    WinLossDraw,
+}
+
+/// A move that is best under perfect play, and the outcome it leads to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    pub square: (usize, usize),
+    /// The final disc differential for the player to move. When solving for a win, loss or
+    /// draw only, this is 1, -1 or 0 respectively.
+    pub score: isize,
+}
+
+/// A move suggested by the companion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub square: (usize, usize),
+    /// Whether the move was taken from the opening book rather than found by searching.
+    pub book: bool,
+}
+
+/// A legal move and its score from the search, for the player to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoredMove {
+    pub square: (usize, usize),
+    pub score: isize,
+}
+
+/// The result of analyzing a position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Analysis {
+    /// The legal moves with their scores, best first. Ties go to the later move in (x, y)
+    /// order.
+    pub moves: Vec<ScoredMove>,
+    /// The line of play expected to follow the best move, starting with the best move itself.
+    /// A `None` entry records a pass.
+    pub principal_variation: Vec<Option<(usize, usize)>>,
+    /// The depth of the deepest search that completed, in plies.
+    pub depth: usize,
+    /// The number of positions searched.
+    pub nodes: u64,
+}
+
+pub struct Companion<'a, E = Positional> {
     game: &'a Game,
-    color: isize,
+    evaluator: E,
+    endgame: usize,
+    table: Option<&'a mut TranspositionTable>,
+    book: Option<&'a Book>,
+    candidates: usize,
 }
 
 impl<'a> From<&'a Game> for Companion<'a> {
     fn from(game: &'a Game) -> Self {
         Self {
             game,
-            color: if game.turn() == Piece::Black { 1 } else { -1 },
+            evaluator: Positional::default(),
+            endgame: ENDGAME_EMPTIES,
+            table: None,
+            book: None,
+            candidates: 1,
         }
     }
 }
 
-impl Companion<'_> {
+impl<'a, E: Evaluator> Companion<'a, E> {
+    /// Uses `evaluator` to score positions instead of the current evaluator.
+    pub fn with_evaluator<T: Evaluator>(self, evaluator: T) -> Companion<'a, T> {
+        Companion {
+            game: self.game,
+            evaluator,
+            endgame: self.endgame,
+            table: self.table,
+            book: self.book,
+            candidates: self.candidates,
+        }
+    }
+
+    /// Solves the game exactly once `empties` or fewer squares remain. Zero disables the
+    /// endgame solver.
+    #[must_use]
+    pub fn with_endgame(self, empties: usize) -> Self {
+        Self {
+            endgame: empties,
+            ..self
+        }
+    }
+
+    /// Caches search results in `table`, so that they can be reused by later searches in the
+    /// same game. Without a table, each search starts with an empty one.
+    #[must_use]
+    pub fn with_table(self, table: &'a mut TranspositionTable) -> Self {
+        Self {
+            table: Some(table),
+            ..self
+        }
+    }
+
+    /// Plays moves from `book` while the game is still in it, instead of searching.
+    #[must_use]
+    pub fn with_book(self, book: &'a Book) -> Self {
+        Self {
+            book: Some(book),
+            ..self
+        }
+    }
+
+    /// Picks at random among the `candidates` best moves found by the search, rather than
+    /// always playing the best one. This makes the companion weaker, since every move has to
+    /// be searched with a full window.
+    #[must_use]
+    pub fn with_candidates(self, candidates: usize) -> Self {
+        Self {
+            candidates: candidates.max(1),
+            ..self
+        }
+    }
+
+    /// Applies the candidates, endgame and opening book parameters of `settings`, using `book`
+    /// if the settings allow it. The depth and time budget are given to
+    /// [`Companion::suggest`] instead.
+    #[must_use]
+    pub fn with_settings(self, settings: &Settings, book: &'a Book) -> Self {
+        Self {
+            candidates: settings.candidates.max(1),
+            endgame: settings.endgame,
+            book: settings.book.then_some(book),
+            ..self
+        }
+    }
+
+    /// Finds the best move under perfect play by searching to the end of the game. Returns
+    /// `None` if the player to move has no legal moves.
+    #[must_use]
+    pub fn solve(&self, mode: Solve) -> Option<Solution> {
+        let mut table = TranspositionTable::default();
+        Search::new(&DiscCount, Some(&mut table), None).solve(
+            *self.game.board(),
+            self.game.turn(),
+            mode,
+        )
+    }
+
+    /// Scores every legal move for the player to move, searching progressively deeper up to
+    /// `depth` plies until `budget`, if any, has elapsed. The opening book, endgame solver and
+    /// candidates setting are not used, so the scores all come from the evaluator.
+    ///
+    /// # Panics
+    /// Panics if the player to move has no legal moves.
+    pub fn analyze(&mut self, depth: usize, budget: Option<Duration>) -> Analysis {
+        let deadline = budget.map(|budget| Instant::now() + budget);
+        self.deepen(depth, deadline, usize::MAX)
+    }
+
+    /// Searches `depth` plies ahead and returns the best move for the player to move.
     pub fn choice(&mut self, depth: usize) -> (usize, usize) {
-        let mut history = vec![];
-        let mut root = self.game.clone();
-        Self::negamax(&mut root, &mut history, depth, self.color);
-        let index = self.game.history().len();
-        history.get(index).copied().unwrap()
+        self.search(depth, None).square
+    }
+
+    /// Searches progressively deeper, up to `depth` plies, until `budget` has elapsed.
+    /// Returns the best move found by the deepest search that completed in time.
+    pub fn timed(&mut self, depth: usize, budget: Duration) -> (usize, usize) {
+        self.suggest(depth, budget).square
+    }
+
+    /// Like [`Companion::timed`], but also reports whether the move came from the book.
+    pub fn suggest(&mut self, depth: usize, budget: Duration) -> Suggestion {
+        self.search(depth, Some(Instant::now() + budget))
+    }
+
+    fn search(&mut self, depth: usize, deadline: Option<Instant>) -> Suggestion {
+        if let Some(square) = self
+            .book
+            .and_then(|book| book.choose(self.game, &mut rand::thread_rng()))
+        {
+            return Suggestion { square, book: true };
+        }
+        let board = *self.game.board();
+        let player = self.game.turn();
+        if board.empty().count_ones() as usize <= self.endgame {
+            // Leave half of the budget for the heuristic search in case the solver runs out
+            // of time.
+            let now = Instant::now();
+            let cutoff = deadline.map(|deadline| now + deadline.saturating_duration_since(now) / 2);
+            // The solver scores positions differently, so it can't share the table.
+            let mut table = TranspositionTable::default();
+            if let Some(solution) =
+                Search::new(&DiscCount, Some(&mut table), cutoff).solve(board, player, Solve::Exact)
+            {
+                return Suggestion {
+                    square: solution.square,
+                    book: false,
+                };
+            }
+        }
+        let mut analysis = self.deepen(depth, deadline, self.candidates);
+        analysis.moves.truncate(self.candidates);
+        let choice = analysis.moves[rand::thread_rng().gen_range(0..analysis.moves.len())];
+        Suggestion {
+            square: choice.square,
+            book: false,
+        }
+    }
+
+    /// Runs iterative deepening, scoring the best `count` moves exactly.
+    fn deepen(&mut self, depth: usize, deadline: Option<Instant>, count: usize) -> Analysis {
+        let board = *self.game.board();
+        let player = self.game.turn();
+        let mut owned = None;
+        let table = match self.table.as_deref_mut() {
+            Some(table) => table,
+            None => owned.insert(TranspositionTable::default()),
+        };
+        let mut search = Search::new(&self.evaluator, Some(table), None);
+        let mut moves = board.squares(board.moves(player));
+        // The first iteration always runs to completion so that there is a move to fall back on.
+        let mut ranked = search
+            .rank(board, player, &moves, 1, count)
+            .expect("searched a position with no legal moves");
+        let mut reached = 1;
+        search.deadline = deadline;
+        for depth in 2..=depth {
+            // Search the best move from the previous iteration first.
+            moves.sort_by_key(|&square| square != ranked[0].0);
+            let Some(next) = search.rank(board, player, &moves, depth, count) else {
+                break;
+            };
+            ranked = next;
+            reached = depth;
+        }
+        Analysis {
+            principal_variation: search.principal_variation(board, player, ranked[0].0, reached),
+            moves: ranked
+                .into_iter()
+                .map(|(square, score)| ScoredMove { square, score })
+                .collect(),
+            depth: reached,
+            nodes: search.nodes,
+        }
+    }
+}
+
+/// The state of a single alpha-beta search, shared across iterative deepening iterations.
+struct Search<'e, E> {
+    evaluator: &'e E,
+    table: Option<&'e mut TranspositionTable>,
+    nodes: u64,
+    deadline: Option<Instant>,
+    aborted: bool,
+    /// Up to two quiet moves per ply that recently caused a cutoff.
+    killers: Vec<[Option<(usize, usize)>; 2]>,
+    /// How often each move caused a cutoff, weighted by the depth it was found at.
+    history: [[u64; MAX_SQUARES]; 2],
+}
+
+impl<'e, E: Evaluator> Search<'e, E> {
+    fn new(
+        evaluator: &'e E,
+        table: Option<&'e mut TranspositionTable>,
+        deadline: Option<Instant>,
+    ) -> Self {
+        Self {
+            evaluator,
+            table,
+            nodes: 0,
+            deadline,
+            aborted: false,
+            killers: vec![],
+            history: [[0; MAX_SQUARES]; 2],
+        }
+    }
+
+    /// Searches every root move and returns the best one along with its score, or `None` if
+    /// the search ran out of time. Ties go to the later move in (x, y) order, matching plain
+    /// negamax over [`Game::moves`].
+    fn root(
+        &mut self,
+        board: Board,
+        player: Piece,
+        moves: &[(usize, usize)],
+        depth: usize,
+    ) -> Option<((usize, usize), isize)> {
+        let mut best = None;
+        let mut value = -INFINITY;
+        for &(x, y) in moves {
+            let mut child = board;
+            child.place(x, y, player);
+            // Searching just below the best score so far keeps tied scores exact.
+            let alpha = best.map_or(-INFINITY, |_| value - 1);
+            let score = -self.negamax(child, !player, depth - 1, 1, -INFINITY, -alpha);
+            if self.aborted {
+                return None;
+            }
+            if best.is_none() || score > value || (score == value && Some((x, y)) > best) {
+                value = score;
+                best = Some((x, y));
+            }
+        }
+        best.map(|best| (best, value))
+    }
+
+    /// Scores every root move and returns them best first, or `None` if the search ran out of
+    /// time. Only the best move is scored exactly unless `count` is more than one, in which
+    /// case every move is.
+    fn rank(
+        &mut self,
+        board: Board,
+        player: Piece,
+        moves: &[(usize, usize)],
+        depth: usize,
+        count: usize,
+    ) -> Option<Vec<((usize, usize), isize)>> {
+        if count <= 1 {
+            return self
+                .root(board, player, moves, depth)
+                .map(|best| vec![best]);
+        }
+        let mut scores = Vec::with_capacity(moves.len());
+        for &(x, y) in moves {
+            let mut child = board;
+            child.place(x, y, player);
+            let score = -self.negamax(child, !player, depth - 1, 1, -INFINITY, INFINITY);
+            if self.aborted {
+                return None;
+            }
+            scores.push(((x, y), score));
+        }
+        // Ties go to the later move, as in `root`.
+        scores.sort_by_key(|&(square, score)| Reverse((score, square)));
+        Some(scores)
+    }
+
+    /// Recovers the line of play following `first` from the table, as searched to `depth`
+    /// plies. The line stops early if the table no longer holds a position along it.
+    fn principal_variation(
+        &self,
+        mut board: Board,
+        mut player: Piece,
+        first: (usize, usize),
+        depth: usize,
+    ) -> Vec<Option<(usize, usize)>> {
+        let mut line = vec![Some(first)];
+        board.place(first.0, first.1, player);
+        player = !player;
+        let mut remaining = depth - 1;
+        while remaining > 0 {
+            let moves = board.moves(player);
+            if moves == 0 {
+                if board.moves(!player) == 0 {
+                    break;
+                }
+                // Passes don't use up any depth, just as in the search.
+                line.push(None);
+                player = !player;
+                continue;
+            }
+            let Some((x, y)) = self
+                .table
+                .as_deref()
+                .and_then(|table| table.probe(zobrist(&board, player)))
+                .filter(|entry| entry.depth == remaining)
+                .and_then(|entry| entry.best)
+                .filter(|&(x, y)| moves & board.bit(x, y) != 0)
+            else {
+                break;
+            };
+            line.push(Some((x, y)));
+            board.place(x, y, player);
+            player = !player;
+            remaining -= 1;
+        }
+        line
+    }
+
+    /// Searches every root move to the end of the game, or returns `None` if the player to
+    /// move has no legal moves or the search ran out of time. Only meaningful when the
+    /// evaluator scores finished games by their disc differential.
+    fn solve(&mut self, board: Board, player: Piece, mode: Solve) -> Option<Solution> {
+        // Every move fills an empty square and passes don't use up any depth, so searching
+        // this deep always reaches the end of the game.
+        let empties = board.empty().count_ones() as usize;
+        let (mut alpha, beta) = match mode {
+            Solve::Exact => (-INFINITY, INFINITY),
+            Solve::WinLossDraw => (-1, 1),
+        };
+        let mut best = None;
+        let mut value = -INFINITY;
+        for (x, y) in self.order(&board, board.moves(player), player, 0, None) {
+            let mut child = board;
+            child.place(x, y, player);
+            let score = -self.negamax(child, !player, empties - 1, 1, -beta, -alpha);
+            if self.aborted {
+                return None;
+            }
+            if score > value {
+                value = score;
+                best = Some((x, y));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        let score = match mode {
+            Solve::Exact => value,
+            Solve::WinLossDraw => value.signum(),
+        };
+        best.map(|square| Solution { square, score })
     }
 
     fn negamax(
-        game: &mut Game,
-        history: &mut Vec<(usize, usize)>,
+        &mut self,
+        board: Board,
+        player: Piece,
         depth: usize,
-        color: isize,
+        ply: usize,
+        mut alpha: isize,
+        beta: isize,
     ) -> isize {
-        if depth == 0 || game.over() {
-            color * Self::heuristic(game)
-        } else {
-            game.moves(Self::player(color))
-                .iter()
-                .fold(isize::MIN, |value, &(x, y)| {
-                    let mut child = game.clone();
-                    child.place(x, y, Self::player(color)).unwrap();
-                    let alt = -Self::negamax(&mut child, history, depth - 1, -color);
-                    if alt > value {
-                        *history = child.history();
-                    }
-                    value.max(alt)
-                })
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL)
+            && self.deadline.is_some_and(|d| Instant::now() >= d)
+        {
+            self.aborted = true;
+        }
+        if self.aborted {
+            return 0;
+        }
+        let moves = board.moves(player);
+        if depth == 0 || (moves == 0 && board.moves(!player) == 0) {
+            return self.evaluator.evaluate(&board, player);
+        }
+        if moves == 0 {
+            // The player to move is stuck but the game isn't over, so they must pass. Passes
+            // are made as part of the previous move, so they don't use up any depth.
+            return -self.negamax(board, !player, depth, ply + 1, -beta, -alpha);
+        }
+        let key = zobrist(&board, player);
+        let entry = self.table.as_deref().and_then(|table| table.probe(key));
+        if let Some(entry) = entry {
+            // Only results searched to exactly this depth are reused, so that the score
+            // doesn't depend on what was searched before.
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if entry.depth == depth && usable {
+                return entry.score;
+            }
+        }
+        let window = alpha;
+        let mut value = -INFINITY;
+        let mut best = None;
+        for (x, y) in self.order(
+            &board,
+            moves,
+            player,
+            ply,
+            entry.and_then(|entry| entry.best),
+        ) {
+            let mut child = board;
+            child.place(x, y, player);
+            let score = -self.negamax(child, !player, depth - 1, ply + 1, -beta, -alpha);
+            if score > value {
+                value = score;
+                best = Some((x, y));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                self.cutoff(&board, (x, y), player, depth, ply);
+                break;
+            }
         }
+        if let Some(table) = self.table.as_deref_mut().filter(|_| !self.aborted) {
+            let bound = match value {
+                _ if value <= window => Bound::Upper,
+                _ if value >= beta => Bound::Lower,
+                _ => Bound::Exact,
+            };
+            table.store(Entry {
+                key,
+                depth,
+                bound,
+                score: value,
+                best,
+            });
+        }
+        value
     }
 
-    fn player(color: isize) -> Piece {
-        if color == 1 {
-            Piece::Black
-        } else {
-            Piece::White
+    /// Sorts moves so that the most promising are searched first: the best move from an
+    /// earlier search of the same position, corners, then killer moves, then by the history
+    /// heuristic, with X and C squares last. Corners can never be flipped, while the squares
+    /// next to them tend to give the corner away, especially diagonally.
+    fn order(
+        &mut self,
+        board: &Board,
+        moves: Bitboard,
+        player: Piece,
+        ply: usize,
+        hint: Option<(usize, usize)>,
+    ) -> Vec<(usize, usize)> {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
         }
+        let killers = self.killers[ply];
+        let history = &self.history[player as usize];
+        let corners = board.corners();
+        let x_squares = board.neighbours(corners) & !board.edges();
+        let c_squares = board.neighbours(corners) & board.edges();
+        let mut moves = board.squares(moves);
+        moves.sort_by_key(|&(x, y)| {
+            let bit = board.bit(x, y);
+            let rank = match bit {
+                _ if bit & corners != 0 => 2,
+                _ if bit & x_squares != 0 => -2,
+                _ if bit & c_squares != 0 => -1,
+                _ => 0,
+            };
+            let killer = killers.contains(&Some((x, y)));
+            let hint = hint == Some((x, y));
+            Reverse((hint, rank, killer, history[x + y * board.width()]))
+        });
+        moves
     }
 
-    #[allow(clippy::cast_possible_wrap)] // 64 <= isize::MAX
-    fn heuristic(game: &mut Game) -> isize {
-        let (black, _) = game.score();
-        assert!(black <= 64);
-        black as isize
+    /// Records a move that caused a beta cutoff for use in move ordering.
+    fn cutoff(
+        &mut self,
+        board: &Board,
+        (x, y): (usize, usize),
+        player: Piece,
+        depth: usize,
+        ply: usize,
+    ) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some((x, y)) {
+            killers[1] = killers[0];
+            killers[0] = Some((x, y));
+        }
+        self.history[player as usize][x + y * board.width()] += (depth * depth) as u64;
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Companion;
-    use crate::{Game, Piece};
+    use super::{Companion, Search, Solution, Solve, INFINITY};
+    use crate::{
+        evaluation::{DiscCount, Evaluator, Positional},
+        Board, Book, Game, Piece, TranspositionTable,
+    };
+    use std::{cmp::Reverse, time::Duration};
+
+    /// A reference search without any pruning.
+    fn negamax(
+        evaluator: &impl Evaluator,
+        board: Board,
+        player: Piece,
+        depth: usize,
+        nodes: &mut u64,
+    ) -> isize {
+        *nodes += 1;
+        let moves = board.moves(player);
+        if depth == 0 || (moves == 0 && board.moves(!player) == 0) {
+            return evaluator.evaluate(&board, player);
+        }
+        if moves == 0 {
+            return -negamax(evaluator, board, !player, depth, nodes);
+        }
+        board
+            .squares(moves)
+            .into_iter()
+            .map(|(x, y)| {
+                let mut child = board;
+                child.place(x, y, player);
+                -negamax(evaluator, child, !player, depth - 1, nodes)
+            })
+            .max()
+            .unwrap()
+    }
+
+    /// Picks the best move using [`negamax`], with ties going to the later move.
+    fn plain(
+        evaluator: &impl Evaluator,
+        game: &Game,
+        depth: usize,
+        nodes: &mut u64,
+    ) -> ((usize, usize), isize) {
+        let player = game.turn();
+        game.moves(player)
+            .into_iter()
+            .map(|(x, y)| {
+                let mut child = *game.board();
+                child.place(x, y, player);
+                (
+                    (x, y),
+                    -negamax(evaluator, child, !player, depth - 1, nodes),
+                )
+            })
+            .fold(
+                None,
+                |best: Option<((usize, usize), isize)>, (square, score)| match best {
+                    Some((_, value)) if value > score => best,
+                    _ => Some((square, score)),
+                },
+            )
+            .unwrap()
+    }
+
+    /// Positions along a game where each side always plays its middle legal move.
+    fn positions() -> Vec<Game> {
+        let mut game = Game::new();
+        let mut positions = vec![];
+        while !game.over() {
+            positions.push(game.clone());
+            let moves = game.moves(game.turn());
+            let (x, y) = moves[moves.len() / 2];
+            game.place(x, y, game.turn()).unwrap();
+        }
+        positions
+    }
+
+    fn matches_negamax(evaluator: &impl Evaluator) {
+        for game in positions().iter().step_by(4) {
+            let board = *game.board();
+            let moves = game.moves(game.turn());
+            let (mut full, mut pruned) = (0, 0);
+            for depth in 1..=4 {
+                let mut search = Search::new(evaluator, None, None);
+                let expected = plain(evaluator, game, depth, &mut full);
+                let actual = search.root(board, game.turn(), &moves, depth);
+                assert_eq!(actual, Some(expected));
+                pruned += search.nodes;
+            }
+            assert!(pruned <= full);
+        }
+    }
+
+    #[test]
+    fn matches_negamax_disc_count() {
+        matches_negamax(&DiscCount);
+    }
+
+    #[test]
+    fn matches_negamax_positional() {
+        matches_negamax(&Positional::default());
+    }
+
+    #[test]
+    fn iterative_deepening() {
+        for game in positions().iter().step_by(6) {
+            let (expected, _) = plain(&Positional::default(), game, 4, &mut 0);
+            let mut companion = Companion::from(game).with_endgame(0);
+            assert_eq!(companion.choice(4), expected);
+        }
+    }
+
+    #[test]
+    fn widths() {
+        for width in [6, 10] {
+            // The search agrees with a plain one on other board sizes too.
+            let mut game = Game::with_width(width).unwrap();
+            for _ in 0..6 {
+                let (expected, _) = plain(&Positional::default(), &game, 3, &mut 0);
+                let mut companion = Companion::from(&game).with_endgame(0);
+                assert_eq!(companion.choice(3), expected);
+                game.place(expected.0, expected.1, game.turn()).unwrap();
+            }
+            // And it can play the game out, solving the endgame once it is close enough.
+            while !game.over() {
+                let (x, y) = Companion::from(&game).with_endgame(10).choice(2);
+                game.place(x, y, game.turn()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn time_budget() {
+        let game = Game::new();
+        let (x, y) = Companion::from(&game).timed(64, std::time::Duration::from_millis(50));
+        assert!(game.moves(Piece::Black).contains(&(x, y)));
+    }
+
+    #[test]
+    fn window() {
+        let game = Game::new();
+        let mut search = Search::new(&DiscCount, None, None);
+        let score = search.negamax(*game.board(), Piece::Black, 3, 0, -INFINITY, INFINITY);
+        assert_eq!(
+            score,
+            negamax(&DiscCount, *game.board(), Piece::Black, 3, &mut 0)
+        );
+    }
+
+    #[test]
+    fn endgame() {
+        // Solved once by brute force: (ply, best move, final disc differential).
+        let expected = [
+            (48, (0, 2), 26),
+            (49, (0, 5), -4),
+            (50, (0, 3), 16),
+            (51, (0, 0), -6),
+            (52, (0, 3), 16),
+            (53, (0, 0), -10),
+            (54, (0, 0), 22),
+        ];
+        let positions = positions();
+        for (ply, square, score) in expected {
+            let game = &positions[ply];
+            let companion = Companion::from(game);
+            assert_eq!(
+                companion.solve(Solve::Exact),
+                Some(Solution { square, score })
+            );
+            let outcome = companion.solve(Solve::WinLossDraw).unwrap();
+            assert_eq!(outcome.score, score.signum());
+            assert_eq!(Companion::from(game).choice(1), square);
+        }
+    }
+
+    #[test]
+    fn endgame_matches_negamax() {
+        for game in positions()
+            .iter()
+            .filter(|game| game.board().empty().count_ones() <= 10)
+        {
+            let board = *game.board();
+            let empties = board.empty().count_ones() as usize;
+            let expected = negamax(&DiscCount, board, game.turn(), empties, &mut 0);
+            let solution = Companion::from(game).solve(Solve::Exact).unwrap();
+            assert_eq!(solution.score, expected);
+            let mut child = board;
+            child.place(solution.square.0, solution.square.1, game.turn());
+            let reply = negamax(&DiscCount, child, !game.turn(), empties - 1, &mut 0);
+            assert_eq!(-reply, expected);
+        }
+    }
+
+    /// Runs iterative deepening up to `depth` and returns each iteration's result.
+    fn deepen<E: Evaluator>(
+        search: &mut Search<'_, E>,
+        game: &Game,
+        depth: usize,
+    ) -> Vec<Option<((usize, usize), isize)>> {
+        let moves = game.moves(game.turn());
+        (1..=depth)
+            .map(|depth| search.root(*game.board(), game.turn(), &moves, depth))
+            .collect()
+    }
+
+    #[test]
+    fn transposition_table() {
+        let evaluator = Positional::default();
+        for game in positions().iter().step_by(4) {
+            let mut plain = Search::new(&evaluator, None, None);
+            let expected = deepen(&mut plain, game, 5);
+            let mut table = TranspositionTable::default();
+            let mut cached = Search::new(&evaluator, Some(&mut table), None);
+            assert_eq!(deepen(&mut cached, game, 5), expected);
+            assert!(cached.nodes < plain.nodes);
+        }
+    }
+
+    #[test]
+    fn reused_table() {
+        let evaluator = Positional::default();
+        let positions = positions();
+        let mut table = TranspositionTable::default();
+        let (mut fresh, mut reused) = (0, 0);
+        for game in positions.iter().step_by(2).take(12) {
+            let mut plain = Search::new(&evaluator, None, None);
+            let expected = deepen(&mut plain, game, 5);
+            let mut empty = TranspositionTable::default();
+            let mut search = Search::new(&evaluator, Some(&mut empty), None);
+            assert_eq!(deepen(&mut search, game, 5), expected);
+            fresh += search.nodes;
+            // The table still holds results from searching the positions before this one.
+            let mut search = Search::new(&evaluator, Some(&mut table), None);
+            assert_eq!(deepen(&mut search, game, 5), expected);
+            reused += search.nodes;
+        }
+        assert!(reused < fresh);
+        // Sharing a table between companions doesn't change their choices either.
+        for game in positions.iter().step_by(6) {
+            let expected = Companion::from(game).choice(5);
+            assert_eq!(
+                Companion::from(game).with_table(&mut table).choice(5),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn book() {
+        let book = Book::openings();
+        let game = Game::new();
+        let suggestion = Companion::from(&game)
+            .with_book(&book)
+            .suggest(4, Duration::from_secs(10));
+        assert!(suggestion.book);
+        assert!(game.moves(game.turn()).contains(&suggestion.square));
+        // This game leaves the book after a few moves.
+        for game in positions().iter().skip(4) {
+            let mut companion = Companion::from(game).with_book(&book);
+            let suggestion = companion.suggest(1, Duration::from_secs(10));
+            assert!(!suggestion.book);
+            assert_eq!(suggestion.square, Companion::from(game).choice(1));
+        }
+    }
+
+    #[test]
+    fn candidates() {
+        let evaluator = Positional::default();
+        for game in positions().iter().step_by(8) {
+            let player = game.turn();
+            let mut scores: Vec<isize> = game
+                .moves(player)
+                .into_iter()
+                .map(|(x, y)| {
+                    let mut child = *game.board();
+                    child.place(x, y, player);
+                    -negamax(&evaluator, child, !player, 2, &mut 0)
+                })
+                .collect();
+            scores.sort_unstable_by_key(|&score| Reverse(score));
+            let worst = scores[scores.len().min(3) - 1];
+            for _ in 0..5 {
+                let mut companion = Companion::from(game).with_candidates(3).with_endgame(0);
+                let (x, y) = companion.choice(3);
+                let mut child = *game.board();
+                child.place(x, y, player);
+                assert!(-negamax(&evaluator, child, !player, 2, &mut 0) >= worst);
+            }
+        }
+    }
+
+    #[test]
+    fn analysis() {
+        let evaluator = Positional::default();
+        for game in positions().iter().step_by(6) {
+            let player = game.turn();
+            let analysis = Companion::from(game).analyze(4, None);
+            assert_eq!(analysis.depth, 4);
+            assert!(analysis.nodes > 0);
+            assert_eq!(analysis.moves.len(), game.moves(player).len());
+            for scored in &analysis.moves {
+                let mut child = *game.board();
+                child.place(scored.square.0, scored.square.1, player);
+                assert_eq!(
+                    scored.score,
+                    -negamax(&evaluator, child, !player, 3, &mut 0)
+                );
+            }
+            assert!(analysis
+                .moves
+                .windows(2)
+                .all(|pair| pair[0].score >= pair[1].score));
+            let (best, _) = plain(&evaluator, game, 4, &mut 0);
+            assert_eq!(analysis.moves[0].square, best);
+            // Playing out the principal variation leads to the position the best score comes
+            // from.
+            let line = &analysis.principal_variation;
+            assert_eq!(line[0], Some(best));
+            let (mut board, mut turn) = (*game.board(), player);
+            for square in line {
+                if let Some((x, y)) = *square {
+                    assert_ne!(board.moves(turn) & board.bit(x, y), 0);
+                    board.place(x, y, turn);
+                } else {
+                    assert_eq!(board.moves(turn), 0);
+                }
+                turn = !turn;
+            }
+            let over = board.moves(turn) == 0 && board.moves(!turn) == 0;
+            assert!(over || line.iter().flatten().count() == 4);
+            assert_eq!(evaluator.evaluate(&board, player), analysis.moves[0].score);
+        }
+    }
 
     #[test]
     fn draw() {
         let mut game = Game::new();
-        let mut piece = Piece::Black;
         while !game.over() {
-            let mut companion = Companion::from(&game);
-            let (x, y) = companion.choice(6);
-            game.place(x, y, piece).unwrap();
-            piece = !piece;
+            let mut companion = Companion::from(&game).with_endgame(9);
+            let (x, y) = companion.choice(1);
+            game.place(x, y, game.turn()).unwrap();
         }
         assert_eq!(game.score(), (32, 32));
     }
+
+    #[test]
+    fn self_play() {
+        let mut game = Game::new();
+        while !game.over() {
+            let mut companion = Companion::from(&game)
+                .with_evaluator(DiscCount)
+                .with_endgame(0);
+            let (x, y) = companion.choice(6);
+            game.place(x, y, game.turn()).unwrap();
+        }
+        assert_eq!(game.score(), (52, 12));
+    }
 }
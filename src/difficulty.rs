@@ -0,0 +1,79 @@
+use crate::ENDGAME_EMPTIES;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How strongly the companion plays.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Beginner,
+    Novice,
+    Intermediate,
+    #[default]
+    Advanced,
+    Expert,
+}
+
+/// The search parameters for a difficulty level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// The deepest the search may go, in plies.
+    pub depth: usize,
+    /// How long the search may take.
+    pub budget: Duration,
+    /// How many of the best moves to pick from at random. See
+    /// [`Companion::with_candidates`](crate::Companion::with_candidates).
+    pub candidates: usize,
+    /// How many empty squares the endgame solver takes over at, or zero to never use it.
+    pub endgame: usize,
+    /// Whether to play from the opening book.
+    pub book: bool,
+}
+
+impl Difficulty {
+    /// The search parameters for this level. Lower levels search less deeply and pick among
+    /// several good moves instead of always playing the best one, so that casual players can
+    /// beat them.
+    #[must_use]
+    pub fn settings(self) -> Settings {
+        let (depth, budget, candidates, endgame, book) = match self {
+            Self::Beginner => (1, 100, 4, 0, false),
+            Self::Novice => (2, 200, 3, 0, false),
+            Self::Intermediate => (4, 300, 2, 8, true),
+            Self::Advanced => (20, 500, 1, ENDGAME_EMPTIES, true),
+            Self::Expert => (20, 2000, 1, ENDGAME_EMPTIES + 2, true),
+        };
+        Settings {
+            depth,
+            budget: Duration::from_millis(budget),
+            candidates,
+            endgame,
+            book,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Difficulty;
+
+    #[test]
+    fn levels() {
+        let levels = [
+            Difficulty::Beginner,
+            Difficulty::Novice,
+            Difficulty::Intermediate,
+            Difficulty::Advanced,
+            Difficulty::Expert,
+        ]
+        .map(Difficulty::settings);
+        // Each level searches at least as hard and makes no more mistakes than the last.
+        for pair in levels.windows(2) {
+            assert!(pair[0].depth <= pair[1].depth);
+            assert!(pair[0].budget <= pair[1].budget);
+            assert!(pair[0].candidates >= pair[1].candidates);
+            assert!(pair[0].endgame <= pair[1].endgame);
+        }
+        assert_eq!(levels[4].candidates, 1);
+    }
+}
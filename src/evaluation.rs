@@ -0,0 +1,250 @@
+use crate::{Bitboard, Board, Piece};
+use serde::{Deserialize, Serialize};
+
+/// The score of a finished game, before the final disc differential is added. Larger than
+/// any score given to an unfinished game so that the search always prefers a won ending.
+const WIN: isize = 1_000_000;
+
+/// Positions with fewer discs than this are scored with the opening weights.
+const MIDGAME: usize = 20;
+/// Positions with at least this many discs are scored with the endgame weights.
+const ENDGAME: usize = 48;
+
+/// A static weight for each square of a standard board, indexed by `x + y * 8`. Corners are
+/// valuable, while the squares next to them tend to give the corner away. Other widths use
+/// the weight of the square the same distance from the nearest edges, up to the center.
+#[rustfmt::skip]
+const SQUARES: [isize; 64] = [
+    100, -20,  10,   5,   5,  10, -20, 100,
+    -20, -50,  -2,  -2,  -2,  -2, -50, -20,
+     10,  -2,  -1,  -1,  -1,  -1,  -2,  10,
+      5,  -2,  -1,  -1,  -1,  -1,  -2,   5,
+      5,  -2,  -1,  -1,  -1,  -1,  -2,   5,
+     10,  -2,  -1,  -1,  -1,  -1,  -2,  10,
+    -20, -50,  -2,  -2,  -2,  -2, -50, -20,
+    100, -20,  10,   5,   5,  10, -20, 100,
+];
+
+/// Scores positions for the companion's search.
+pub trait Evaluator {
+    /// Scores `board` from the perspective of `piece`, where higher is better. Scores should
+    /// be antisymmetric: evaluating for the opponent should give the negated score.
+    fn evaluate(&self, board: &Board, piece: Piece) -> isize;
+}
+
+impl<E: Evaluator + ?Sized> Evaluator for Box<E> {
+    fn evaluate(&self, board: &Board, piece: Piece) -> isize {
+        (**self).evaluate(board, piece)
+    }
+}
+
+/// Scores a position by its disc differential alone.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiscCount;
+
+impl Evaluator for DiscCount {
+    #[allow(clippy::cast_possible_wrap)] // 100 <= isize::MAX
+    fn evaluate(&self, board: &Board, piece: Piece) -> isize {
+        board.count(piece) as isize - board.count(!piece) as isize
+    }
+}
+
+/// How much each feature of a position contributes to its score. Every feature except
+/// `squares` is scaled to lie between -100 and 100 before it is weighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Weights {
+    /// The number of legal moves available.
+    pub mobility: isize,
+    /// The number of empty squares next to opponent discs, which are likely future moves.
+    pub potential_mobility: isize,
+    /// The number of corners held.
+    pub corners: isize,
+    /// The number of discs that can no longer be flipped.
+    pub stability: isize,
+    /// The number of discs next to an empty square, which is better kept low.
+    pub frontier: isize,
+    /// The sum of the static square weights of each disc.
+    pub squares: isize,
+    /// The number of discs held.
+    pub discs: isize,
+}
+
+/// Scores a position by a weighted combination of positional features, with weights that
+/// depend on how far the game has progressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Positional {
+    pub opening: Weights,
+    pub midgame: Weights,
+    pub endgame: Weights,
+}
+
+impl Default for Positional {
+    fn default() -> Self {
+        Self {
+            opening: Weights {
+                mobility: 80,
+                potential_mobility: 40,
+                corners: 800,
+                stability: 100,
+                frontier: 75,
+                squares: 10,
+                discs: 0,
+            },
+            midgame: Weights {
+                mobility: 80,
+                potential_mobility: 30,
+                corners: 800,
+                stability: 200,
+                frontier: 75,
+                squares: 10,
+                discs: 10,
+            },
+            endgame: Weights {
+                mobility: 40,
+                potential_mobility: 10,
+                corners: 600,
+                stability: 300,
+                frontier: 20,
+                squares: 5,
+                discs: 100,
+            },
+        }
+    }
+}
+
+impl Positional {
+    /// The weights used for a position with `discs` discs on a standard 8x8 board. Positions
+    /// on other boards are scaled to match.
+    #[must_use]
+    pub fn weights(&self, discs: usize) -> &Weights {
+        match discs {
+            _ if discs < MIDGAME => &self.opening,
+            _ if discs < ENDGAME => &self.midgame,
+            _ => &self.endgame,
+        }
+    }
+}
+
+impl Evaluator for Positional {
+    fn evaluate(&self, board: &Board, piece: Piece) -> isize {
+        let own = board.pieces(piece);
+        let opponent = board.pieces(!piece);
+        let (moves, replies) = (board.moves(piece), board.moves(!piece));
+        if moves == 0 && replies == 0 {
+            let discs = DiscCount.evaluate(board, piece);
+            return discs.signum() * WIN + discs;
+        }
+        let width = board.width();
+        let discs = (own | opponent).count_ones() as usize * 64 / (width * width);
+        let weights = self.weights(discs);
+        let empty = board.empty();
+        let mobility = ratio(moves, replies);
+        let potential = ratio(
+            board.neighbours(opponent) & empty,
+            board.neighbours(own) & empty,
+        );
+        let corners = board.corners();
+        let corners = 25 * (count(own & corners) - count(opponent & corners));
+        let stability = ratio(board.stable(piece), board.stable(!piece));
+        let frontier = ratio(
+            board.neighbours(empty) & opponent,
+            board.neighbours(empty) & own,
+        );
+        let squares = weight(board, own) - weight(board, opponent);
+        let discs = ratio(own, opponent);
+        weights.mobility * mobility
+            + weights.potential_mobility * potential
+            + weights.corners * corners
+            + weights.stability * stability
+            + weights.frontier * frontier
+            + weights.squares * squares
+            + weights.discs * discs
+    }
+}
+
+#[allow(clippy::cast_possible_wrap)] // 100 <= isize::MAX
+fn count(bits: Bitboard) -> isize {
+    bits.count_ones() as isize
+}
+
+/// Compares the sizes of two sets of squares on a scale from -100 to 100.
+fn ratio(ours: Bitboard, theirs: Bitboard) -> isize {
+    let (ours, theirs) = (count(ours), count(theirs));
+    if ours + theirs == 0 {
+        0
+    } else {
+        100 * (ours - theirs) / (ours + theirs)
+    }
+}
+
+/// The sum of the static square weights of the squares in `bits`.
+fn weight(board: &Board, bits: Bitboard) -> isize {
+    let width = board.width();
+    // Maps a coordinate to the one on a standard board at the same distance from the edge.
+    let standard = |i: usize| {
+        if i < width / 2 {
+            i.min(3)
+        } else {
+            7 - (width - 1 - i).min(3)
+        }
+    };
+    board
+        .squares(bits)
+        .into_iter()
+        .map(|(x, y)| SQUARES[standard(x) + standard(y) * 8])
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiscCount, Evaluator, Positional, WIN};
+    use crate::{Board, Game, Piece, WIDTHS};
+
+    #[test]
+    fn antisymmetric() {
+        let evaluator = Positional::default();
+        for width in WIDTHS {
+            let mut game = Game::with_width(width).unwrap();
+            while !game.over() {
+                let board = game.board();
+                assert_eq!(
+                    evaluator.evaluate(board, Piece::Black),
+                    -evaluator.evaluate(board, Piece::White)
+                );
+                let moves = game.moves(game.turn());
+                let (x, y) = moves[moves.len() / 2];
+                game.place(x, y, game.turn()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn corner() {
+        // Black can take the top left corner or make a quiet move in the center.
+        let mut board = Board::new();
+        board.set(1, 1, Some(Piece::White));
+        board.set(2, 2, Some(Piece::White));
+        board.set(3, 3, Some(Piece::Black));
+        let evaluator = Positional::default();
+        let mut corner = board;
+        corner.place(0, 0, Piece::Black);
+        let mut elsewhere = board;
+        elsewhere.place(5, 4, Piece::Black);
+        assert!(
+            evaluator.evaluate(&corner, Piece::Black)
+                > evaluator.evaluate(&elsewhere, Piece::Black)
+        );
+    }
+
+    #[test]
+    fn finished() {
+        // White has been wiped out, so neither player can move.
+        let mut board = Board::new();
+        board.set(3, 3, Some(Piece::Black));
+        board.set(4, 4, Some(Piece::Black));
+        let evaluator = Positional::default();
+        assert_eq!(evaluator.evaluate(&board, Piece::Black), WIN + 4);
+        assert_eq!(evaluator.evaluate(&board, Piece::White), -WIN - 4);
+        assert_eq!(DiscCount.evaluate(&board, Piece::White), -4);
+    }
+}
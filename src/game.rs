@@ -1,15 +1,32 @@
 use crate::{
-    board::{Board, Piece},
-    PlaceError,
+    board::{Bitboard, Board, Piece, WIDTHS},
+    notation, GameResult, NotationError, PlaceError, PositionError, Reason,
 };
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, str::FromStr};
+
+/// One entry of a game's history, as seen when stepping through the game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    /// The square played, or `None` if the player passed.
+    pub square: Option<(usize, usize)>,
+    pub piece: Piece,
+    /// The discs that the move flipped.
+    pub flips: Vec<(usize, usize)>,
+    /// The number of black and white discs on the board after the move.
+    pub score: (usize, usize),
+}
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Game {
     board: Board,
     turn: Piece,
-    history: Vec<(usize, usize)>,
+    /// The squares played so far, in order. A `None` entry records a pass.
+    history: Vec<Option<(usize, usize)>>,
+    /// The position and player to move that the game started from, if it didn't start from
+    /// the standard position.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<(Board, Piece)>,
 }
 
 impl Game {
@@ -19,41 +36,120 @@ impl Game {
             board: Board::new(),
             turn: Piece::Black,
             history: Vec::new(),
+            start: None,
         }
     }
 
+    /// Starts a game on a `width` by `width` board, which is one of [`WIDTHS`].
+    ///
+    /// # Errors
+    /// Returns an error if boards of that width aren't supported.
+    pub fn with_width(width: usize) -> Result<Self, PositionError> {
+        if !WIDTHS.contains(&width) {
+            return Err(PositionError::Width(width));
+        }
+        let board = Board::with_width(width);
+        Ok(Self {
+            board,
+            turn: Piece::Black,
+            history: Vec::new(),
+            start: Some((board, Piece::Black)).filter(|&(board, _)| board != Board::new()),
+        })
+    }
+
+    /// Starts a game from a position written as in [`Board::from_position`], with `side` to
+    /// move. If `side` has no legal move, it has to [`pass`](Game::pass) first.
+    ///
+    /// # Errors
+    /// Returns an error if the position is malformed.
+    pub fn from_position(board: &str, side: Piece) -> Result<Self, PositionError> {
+        let board = Board::from_position(board)?;
+        let start = (board, side);
+        Ok(Self {
+            board,
+            turn: side,
+            history: Vec::new(),
+            start: Some(start).filter(|&start| start != (Board::new(), Piece::Black)),
+        })
+    }
+
+    /// The current position followed by the player to move, such as `---...--- X`. This is
+    /// the inverse of parsing a game with [`str::parse`].
     #[must_use]
-    pub fn score(&self) -> (usize, usize) {
-        let mut black = 0;
-        let mut white = 0;
-        for (x, y) in Self::points() {
-            match self.board[(x, y)] {
-                Some(Piece::Black) => black += 1,
-                Some(Piece::White) => white += 1,
-                None => (),
-            }
+    pub fn position(&self) -> String {
+        let side = match self.turn {
+            Piece::Black => 'X',
+            Piece::White => 'O',
+        };
+        format!("{} {side}", self.board.position())
+    }
+
+    /// The game as it was before any moves were made.
+    #[must_use]
+    pub fn start(&self) -> Self {
+        let (board, turn) = self.start.unwrap_or((Board::new(), Piece::Black));
+        Self {
+            board,
+            turn,
+            history: Vec::new(),
+            start: self.start,
         }
-        (black, white)
     }
 
-    pub fn moves(&mut self, piece: Piece) -> Vec<(usize, usize)> {
-        Self::points()
+    /// The width of the board the game is played on.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.board.width()
+    }
+
+    #[must_use]
+    pub fn score(&self) -> (usize, usize) {
+        (
+            self.board.count(Piece::Black),
+            self.board.count(Piece::White),
+        )
+    }
+
+    #[must_use]
+    pub fn moves(&self, piece: Piece) -> Vec<(usize, usize)> {
+        let moves = self.board.moves(piece);
+        self.points()
             .into_iter()
-            .filter(|&(x, y)| self.validate(x, y, piece).is_ok())
+            .filter(|&(x, y)| moves & self.board.bit(x, y) != 0)
             .collect()
     }
 
-    fn points() -> impl IntoIterator<Item = (usize, usize)> {
-        (0..Board::width()).flat_map(|x| (0..Board::width()).map(move |y| (x, y)))
+    fn points(&self) -> impl IntoIterator<Item = (usize, usize)> {
+        let width = self.width();
+        (0..width).flat_map(move |x| (0..width).map(move |y| (x, y)))
     }
 
     /// # Errors
     /// Returns an error if the move is invalid.
     pub fn place(&mut self, x: usize, y: usize, piece: Piece) -> Result<(), PlaceError> {
         self.validate(x, y, piece)?;
-        self.board[(x, y)] = Some(piece);
-        self.board.flip(x, y, piece, true);
-        self.history.push((x, y));
+        self.board.place(x, y, piece);
+        self.history.push(Some((x, y)));
+        self.turn = !self.turn;
+        // A player with no legal moves must pass, but the game only ends once
+        // neither player can move.
+        if self.board.moves(self.turn) == 0 && self.board.moves(!self.turn) != 0 {
+            self.history.push(None);
+            self.turn = !self.turn;
+        }
+        Ok(())
+    }
+
+    /// Passes the turn to the other player. Passes are normally made automatically
+    /// by [`Game::place`], so this is only needed for positions where the player to
+    /// move was already stuck.
+    /// # Errors
+    /// Returns an error if the player to move has a legal move, or if the game is over.
+    pub fn pass(&mut self) -> Result<(), PlaceError> {
+        if self.board.moves(self.turn) != 0 || self.board.moves(!self.turn) == 0 {
+            return Err(PlaceError::Pass(self.turn));
+        }
+        self.history.push(None);
         self.turn = !self.turn;
         Ok(())
     }
@@ -61,17 +157,17 @@ impl Game {
     /// # Errors
     /// Returns an error if the move is invalid.
     pub fn preview(
-        &mut self,
+        &self,
         x: usize,
         y: usize,
         piece: Piece,
     ) -> Result<Vec<(usize, usize)>, PlaceError> {
         self.validate(x, y, piece)?;
-        Ok(self.board.flip(x, y, piece, false))
+        Ok(self.board.squares(self.board.flips(x, y, piece)))
     }
 
-    fn validate(&mut self, x: usize, y: usize, piece: Piece) -> Result<(), PlaceError> {
-        if x >= Board::width() || y >= Board::width() {
+    fn validate(&self, x: usize, y: usize, piece: Piece) -> Result<(), PlaceError> {
+        if x >= self.width() || y >= self.width() {
             Err(PlaceError::OutOfBounds(x, y))
         } else {
             match (
@@ -82,20 +178,115 @@ impl Game {
                 (false, _, _) => Err(PlaceError::Turn(piece)),
                 (_, false, _) => Err(PlaceError::NotAdjacent(x, y)),
                 (_, _, false) => Err(PlaceError::Occupied(x, y)),
-                _ if self.board.flip(x, y, piece, false).is_empty() => {
-                    Err(PlaceError::NoFlips(x, y))
-                }
+                _ if self.board.flips(x, y, piece) == 0 => Err(PlaceError::NoFlips(x, y)),
                 _ => Ok(()),
             }
         }
     }
 
-    pub fn over(&mut self) -> bool {
-        self.moves(self.turn).is_empty()
+    /// The game is over once neither player has a legal move.
+    #[must_use]
+    pub fn over(&self) -> bool {
+        self.board.moves(Piece::Black) == 0 && self.board.moves(Piece::White) == 0
+    }
+
+    /// The result of the game once it has been played out, or `None` while either player
+    /// can still move.
+    #[must_use]
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.over() {
+            return None;
+        }
+        let (black, white) = self.score();
+        let reason = if black + white == self.width() * self.width() {
+            Reason::BoardFull
+        } else {
+            Reason::NoMoves
+        };
+        Some(GameResult::played((black, white), reason))
+    }
+
+    /// The result of ending the game early for `reason`, which `loser` loses regardless of
+    /// the discs on the board. Without a loser, such as when the game is aborted, nobody wins.
+    #[must_use]
+    pub fn forfeit(&self, reason: Reason, loser: Option<Piece>) -> GameResult {
+        GameResult {
+            winner: loser.map(|loser| !loser),
+            score: self.score(),
+            reason,
+        }
+    }
+
+    /// Plays out a game from the standard position using a transcript such as `f5d6c3d3c4`,
+    /// in which passes are left out.
+    ///
+    /// # Errors
+    /// Returns an error if the transcript can't be parsed, or if one of its moves is illegal.
+    pub fn from_transcript(transcript: &str) -> Result<Self, NotationError> {
+        let mut game = Self::new();
+        for (i, (x, y)) in notation::squares(transcript)?.into_iter().enumerate() {
+            game.place(x, y, game.turn)
+                .map_err(|e| NotationError::Move(i + 1, e))?;
+        }
+        Ok(game)
+    }
+
+    /// The moves made so far as a transcript, the inverse of [`Game::from_transcript`].
+    #[must_use]
+    pub fn transcript(&self) -> String {
+        let squares: Vec<_> = self.history.iter().flatten().copied().collect();
+        notation::transcript(&squares)
+    }
+
+    /// The game as it stood after the first `ply` entries of its history, or as it stands now
+    /// if `ply` is past the end of the history.
+    #[must_use]
+    pub fn at(&self, ply: usize) -> Self {
+        let mut game = self.start();
+        for &square in self.history.iter().take(ply) {
+            game.replay(square);
+        }
+        game
     }
 
+    /// Every move and pass made in the game, in order, with what each one changed.
     #[must_use]
-    pub fn history(&self) -> Vec<(usize, usize)> {
+    pub fn steps(&self) -> Vec<Step> {
+        let mut game = self.start();
+        self.history
+            .iter()
+            .map(|&square| {
+                let piece = game.turn;
+                let flips = game.replay(square);
+                Step {
+                    square,
+                    piece,
+                    flips: game.board.squares(flips),
+                    score: game.score(),
+                }
+            })
+            .collect()
+    }
+
+    /// Takes back the last move or pass, leaving the game exactly as it was before it was
+    /// made. Returns what was taken back, or `None` if nothing has been played yet.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.steps().pop()?;
+        *self = self.at(self.history.len() - 1);
+        Some(step)
+    }
+
+    /// Replays one entry of a history that is already known to be legal, returning the discs
+    /// it flipped.
+    fn replay(&mut self, square: Option<(usize, usize)>) -> Bitboard {
+        let flips = square.map_or(0, |(x, y)| self.board.place(x, y, self.turn));
+        self.history.push(square);
+        self.turn = !self.turn;
+        flips
+    }
+
+    #[must_use]
+    pub fn history(&self) -> Vec<Option<(usize, usize)>> {
         self.history.clone()
     }
 
@@ -103,6 +294,10 @@ impl Game {
     pub fn turn(&self) -> Piece {
         self.turn
     }
+
+    pub(crate) fn board(&self) -> &Board {
+        &self.board
+    }
 }
 
 impl Default for Game {
@@ -111,6 +306,22 @@ impl Default for Game {
     }
 }
 
+impl FromStr for Game {
+    type Err = PositionError;
+
+    /// Parses the current position and player to move, as written by [`Game::position`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_end();
+        let (board, side) = s.split_at(s.len() - s.chars().next_back().map_or(0, char::len_utf8));
+        let side = match side {
+            "X" | "x" => Piece::Black,
+            "O" | "o" => Piece::White,
+            _ => return Err(PositionError::Side(side.to_string())),
+        };
+        Self::from_position(board, side)
+    }
+}
+
 impl fmt::Debug for Game {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Turn: {:?}", self.turn)?;
@@ -128,6 +339,16 @@ impl fmt::Display for Game {
 #[cfg(test)]
 mod tests {
     use super::{Game, Piece, PlaceError};
+    use crate::{NotationError, Outcome, PositionError, Reason, WIDTHS};
+
+    /// Black opens at (5, 4), White replies diagonally at (5, 5), then Black plays (4, 5).
+    fn opening() -> Game {
+        let mut state = Game::new();
+        for (x, y) in [(5, 4), (5, 5), (4, 5)] {
+            state.place(x, y, state.turn).unwrap();
+        }
+        state
+    }
 
     #[test]
     fn new() {
@@ -138,14 +359,14 @@ mod tests {
 
     #[test]
     fn initial_moves() {
-        let mut state = Game::new();
+        let state = Game::new();
         let moves = state.moves(Piece::Black);
         assert_eq!(moves.len(), 4);
     }
 
     #[test]
     fn flips_preview() {
-        let mut state = Game::new();
+        let state = Game::new();
         let flips = state.preview(2, 3, Piece::Black);
         assert_eq!(flips.unwrap(), vec![(3, 3)]);
     }
@@ -183,6 +404,35 @@ mod tests {
         assert_eq!(outcome.unwrap_err(), PlaceError::NotAdjacent(0, 0));
     }
 
+    /// A position where Black's move at (2, 0) leaves White without a reply.
+    fn stuck() -> Game {
+        let mut state = Game::new();
+        for (x, y) in [(3, 3), (4, 3), (3, 4), (4, 4)] {
+            state.board.set(x, y, None);
+        }
+        state.board.set(0, 0, Some(Piece::Black));
+        state.board.set(1, 0, Some(Piece::White));
+        state.board.set(3, 0, Some(Piece::White));
+        state
+    }
+
+    #[test]
+    fn automatic_pass() {
+        let mut state = stuck();
+        state.place(2, 0, Piece::Black).unwrap();
+        assert_eq!(state.turn, Piece::Black);
+        assert_eq!(state.history(), vec![Some((2, 0)), None]);
+        assert!(!state.over());
+        state.place(4, 0, Piece::Black).unwrap();
+        assert!(state.over());
+    }
+
+    #[test]
+    fn illegal_pass() {
+        let mut state = Game::new();
+        assert_eq!(state.pass().unwrap_err(), PlaceError::Pass(Piece::Black));
+    }
+
     #[test]
     fn out_of_bounds() {
         let mut state = Game::new();
@@ -190,4 +440,206 @@ mod tests {
         let outcome = state.place(8, 8, Piece::Black);
         assert_eq!(outcome.unwrap_err(), PlaceError::OutOfBounds(8, 8));
     }
+
+    #[test]
+    fn result() {
+        let mut state = stuck();
+        assert_eq!(state.result(), None);
+        state.place(2, 0, Piece::Black).unwrap();
+        state.place(4, 0, Piece::Black).unwrap();
+        // White was wiped out well before the board filled up.
+        let result = state.result().unwrap();
+        assert_eq!(result.winner, Some(Piece::Black));
+        assert_eq!(result.score, (5, 0));
+        assert_eq!(result.reason, Reason::NoMoves);
+        assert_eq!(result.outcome(Piece::Black), Outcome::Win);
+        assert_eq!(result.outcome(Piece::White), Outcome::Loss);
+    }
+
+    #[test]
+    fn draw() {
+        let mut state = Game::new();
+        for x in 0..8 {
+            for y in 0..8 {
+                let piece = if y < 4 { Piece::Black } else { Piece::White };
+                state.board.set(x, y, Some(piece));
+            }
+        }
+        let result = state.result().unwrap();
+        assert_eq!(result.winner, None);
+        assert_eq!(result.score, (32, 32));
+        assert_eq!(result.reason, Reason::BoardFull);
+        assert_eq!(result.outcome(Piece::White), Outcome::Draw);
+    }
+
+    #[test]
+    fn forfeit() {
+        let state = Game::new();
+        let result = state.forfeit(Reason::Resignation, Some(Piece::Black));
+        assert_eq!(result.winner, Some(Piece::White));
+        assert_eq!(result.score, (2, 2));
+        assert_eq!(state.forfeit(Reason::Agreement, None).winner, None);
+    }
+
+    #[test]
+    fn at() {
+        let state = opening();
+        assert_eq!(state.at(0), Game::new());
+        let mut first = Game::new();
+        first.place(5, 4, Piece::Black).unwrap();
+        assert_eq!(state.at(1), first);
+        assert_eq!(state.at(3), state);
+        assert_eq!(state.at(10), state);
+    }
+
+    #[test]
+    fn steps() {
+        let steps = opening().steps();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].square, Some((5, 4)));
+        assert_eq!(steps[0].piece, Piece::Black);
+        assert_eq!(steps[0].flips, vec![(4, 4)]);
+        assert_eq!(steps[0].score, (4, 1));
+        assert_eq!(steps[1].piece, Piece::White);
+        assert_eq!(steps[1].score, (3, 3));
+        // Passes are steps too, made by the player who couldn't move.
+        let mut state = Game::new();
+        while !state.over() {
+            let (x, y) = state.moves(state.turn)[0];
+            state.place(x, y, state.turn).unwrap();
+        }
+        let steps = state.steps();
+        let (ply, pass) = steps
+            .iter()
+            .enumerate()
+            .find(|(_, step)| step.square.is_none())
+            .unwrap();
+        assert_eq!(pass.piece, !steps[ply - 1].piece);
+        assert!(pass.flips.is_empty());
+        assert_eq!(pass.score, steps[ply - 1].score);
+        assert_eq!(state.at(steps.len()), state);
+    }
+
+    #[test]
+    fn transcripts() {
+        let state = Game::from_transcript("f5f6e6").unwrap();
+        assert_eq!(state, opening());
+        assert_eq!(state.transcript(), "f5f6e6");
+        // Passes are made automatically, so they don't appear in the transcript.
+        let mut state = Game::new();
+        while !state.over() {
+            let (x, y) = state.moves(state.turn)[0];
+            state.place(x, y, state.turn).unwrap();
+        }
+        assert!(state.history().contains(&None));
+        assert_eq!(Game::from_transcript(&state.transcript()).unwrap(), state);
+        assert_eq!(
+            Game::from_transcript("f5f5").unwrap_err(),
+            NotationError::Move(2, PlaceError::Occupied(5, 4))
+        );
+    }
+
+    #[test]
+    fn positions() {
+        let state = opening();
+        let position = state.position();
+        assert!(position.ends_with(" O"));
+        let restored: Game = position.parse().unwrap();
+        assert_eq!(restored.board, state.board);
+        assert_eq!(restored.turn, Piece::White);
+        assert_eq!(restored.position(), position);
+        assert_eq!(
+            Game::from_position(&Game::new().board.position(), Piece::Black).unwrap(),
+            Game::new()
+        );
+        assert_eq!(
+            format!("{} Z", Game::new().board.position())
+                .parse::<Game>()
+                .unwrap_err(),
+            PositionError::Side("Z".to_string())
+        );
+        assert_eq!(
+            Game::from_position("X", Piece::Black).unwrap_err(),
+            PositionError::Length(1)
+        );
+    }
+
+    #[test]
+    fn from_position() {
+        // An endgame with only the last two squares of the bottom row empty.
+        let position = format!("{}{}", "X".repeat(62), "--")
+            .replacen('X', "O", 1)
+            .replace("XX--", "XO--");
+        let mut state = Game::from_position(&position, Piece::Black).unwrap();
+        assert_eq!(state.score(), (60, 2));
+        state.place(6, 7, Piece::Black).unwrap();
+        assert!(!state.over());
+        // Replays start from the position the game started from, rather than the standard
+        // one.
+        assert_eq!(state.at(0).position(), format!("{position} X"));
+        assert_eq!(state.steps()[0].flips, vec![(5, 7)]);
+        assert_eq!(state.start().history(), vec![]);
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(Game::with_width(8).unwrap(), Game::new());
+        assert_eq!(Game::with_width(7).unwrap_err(), PositionError::Width(7));
+        for width in WIDTHS {
+            let mut game = Game::with_width(width).unwrap();
+            assert_eq!(game.width(), width);
+            assert_eq!(game.score(), (2, 2));
+            assert_eq!(
+                game.place(width, 0, Piece::Black).unwrap_err(),
+                PlaceError::OutOfBounds(width, 0)
+            );
+            while !game.over() {
+                let moves = game.moves(game.turn());
+                let (x, y) = moves[0];
+                game.place(x, y, game.turn()).unwrap();
+            }
+            // Replays and serialized games keep the size of the board.
+            assert_eq!(game.at(0), game.start());
+            assert_eq!(game.start().width(), width);
+            let json = serde_json::to_string(&game).unwrap();
+            assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+            let (black, white) = game.score();
+            assert!(black + white <= width * width);
+        }
+    }
+
+    #[test]
+    fn undo() {
+        let mut state = opening();
+        let step = state.undo().unwrap();
+        assert_eq!(step.square, Some((4, 5)));
+        assert_eq!(step.piece, Piece::Black);
+        assert_eq!(state, Game::from_transcript("f5f6").unwrap());
+        assert_eq!(state.turn(), Piece::Black);
+        // Every position along a game comes back exactly, passes included.
+        let mut state = Game::new();
+        assert_eq!(state.undo(), None);
+        let mut previous = vec![];
+        while !state.over() {
+            previous.push(state.clone());
+            let (x, y) = state.moves(state.turn)[0];
+            state.place(x, y, state.turn).unwrap();
+        }
+        assert!(state.history().contains(&None));
+        while let Some(expected) = previous.pop() {
+            while state.history().len() > expected.history().len() {
+                state.undo().unwrap();
+            }
+            assert_eq!(state, expected);
+            assert_eq!(state.board(), expected.board());
+        }
+        assert_eq!(state, Game::new());
+        // Games started from a position are taken back to that position.
+        let mut state = Game::with_width(6).unwrap();
+        let start = state.clone();
+        state.place(3, 4, Piece::Black).unwrap();
+        state.undo().unwrap();
+        assert_eq!(state, start);
+        assert_eq!(state.undo(), None);
+    }
 }
@@ -0,0 +1,411 @@
+//! Reading and writing games in the Generic Game Format used by the Generic Game Server and
+//! most Othello programs.
+//!
+//! A record looks like `(;GM[Othello]PB[alice]PW[bob]TY[8]RE[+18.000]BO[...]B[f5]W[d6];)`.
+//! Games of Othello on 6x6, 8x8 and 10x10 boards are supported, and they can start from any
+//! position. Other properties such as ratings and clocks are ignored.
+
+use crate::{notation, Game, GameResult, Piece, PlaceError, PositionError, Reason, Record, WIDTHS};
+use std::fmt::Write;
+
+/// A property of a record, such as `PB[alice]`, as its key and value.
+type Property = (String, String);
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum GgfError {
+    #[error("record {0}: expected {1}")]
+    Syntax(usize, &'static str),
+    #[error("record {0}: {1:?} games are not supported")]
+    Unsupported(usize, String),
+    #[error("record {0}: {1:?} is not a move")]
+    Square(usize, String),
+    #[error("record {0}: {1:?} is not a result")]
+    Result(usize, String),
+    #[error("record {0}, move {1}: {2}")]
+    Move(usize, usize, PlaceError),
+    #[error("record {0}: {1}")]
+    Position(usize, PositionError),
+}
+
+/// Reads every record in `text`. Anything outside of a record is ignored.
+///
+/// Results are given as Black's margin of victory, so the score is taken from the board
+/// instead. A result without a reason is put down to resignation if the game wasn't played
+/// out.
+///
+/// # Errors
+/// Returns an error for the first record that is malformed, isn't a standard game of
+/// Othello, or contains an illegal move.
+pub fn read(text: &str) -> Result<Vec<Record>, GgfError> {
+    let mut records = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("(;") {
+        let number = records.len() + 1;
+        let (properties, remaining) = properties(number, &rest[start + 2..])?;
+        records.push(record(number, &properties)?);
+        rest = remaining;
+    }
+    Ok(records)
+}
+
+/// Writes `record` as a single line. Passes are written out explicitly, as `pa`.
+#[must_use]
+pub fn write(record: &Record) -> String {
+    let result = record
+        .result
+        .map(|result| margin(result, record.game.width()))
+        .map(|result| format!("RE[{result}]"))
+        .unwrap_or_default();
+    let moves = record
+        .game
+        .steps()
+        .into_iter()
+        .fold(String::new(), |mut moves, step| {
+            let color = match step.piece {
+                Piece::Black => "B",
+                Piece::White => "W",
+            };
+            let square = step.square.map_or_else(|| "pa".to_string(), notation::name);
+            // Writing to a `String` can't fail.
+            let _ = write!(moves, "{color}[{square}]");
+            moves
+        });
+    format!(
+        "(;GM[Othello]PB[{}]PW[{}]{result}TY[{}]BO[{}]{moves};)",
+        escape(&record.black),
+        escape(&record.white),
+        record.game.width(),
+        board(&record.game.start())
+    )
+}
+
+/// Splits the body of a record into its properties, returning them along with the text
+/// after the record.
+fn properties(number: usize, text: &str) -> Result<(Vec<Property>, &str), GgfError> {
+    let mut properties = vec![];
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        if let Some(remaining) = rest.strip_prefix(";)") {
+            return Ok((properties, remaining));
+        }
+        let end = rest
+            .find(|c: char| !c.is_ascii_uppercase())
+            .unwrap_or(rest.len());
+        let (key, remaining) = rest.split_at(end);
+        let remaining = remaining
+            .strip_prefix('[')
+            .filter(|_| !key.is_empty())
+            .ok_or(GgfError::Syntax(
+                number,
+                "a property or the end of the record",
+            ))?;
+        // Values end at the first unescaped closing bracket.
+        let mut value = String::new();
+        let mut chars = remaining.char_indices();
+        let close = loop {
+            match chars.next() {
+                Some((_, '\\')) => value.extend(chars.next().map(|(_, c)| c)),
+                Some((i, ']')) => break i,
+                Some((_, c)) => value.push(c),
+                None => return Err(GgfError::Syntax(number, "the end of a property")),
+            }
+        };
+        properties.push((key.to_string(), value));
+        rest = &remaining[close + 1..];
+    }
+}
+
+fn record(number: usize, properties: &[Property]) -> Result<Record, GgfError> {
+    let mut game = Game::new();
+    let (mut black, mut white, mut result) = (String::new(), String::new(), None);
+    let mut moves = 0;
+    for (key, value) in properties {
+        let color = match key.as_str() {
+            "GM" if !value.eq_ignore_ascii_case("othello") => {
+                return Err(GgfError::Unsupported(number, value.clone()))
+            }
+            "TY" => {
+                let width = value.trim().parse().ok().filter(|w| WIDTHS.contains(w));
+                match width {
+                    Some(width) if width == game.width() => (),
+                    // The board can only be resized before anything has been put on it.
+                    Some(width) if game == Game::new() => {
+                        game =
+                            Game::with_width(width).map_err(|e| GgfError::Position(number, e))?;
+                    }
+                    _ => return Err(GgfError::Unsupported(number, value.clone())),
+                }
+                continue;
+            }
+            "BO" if moves > 0 => {
+                return Err(GgfError::Syntax(number, "the board before any moves"))
+            }
+            "BO" => {
+                game = parse_board(number, value)?;
+                continue;
+            }
+            "PB" => {
+                black.clone_from(value);
+                continue;
+            }
+            "PW" => {
+                white.clone_from(value);
+                continue;
+            }
+            "RE" => {
+                result = Some(value);
+                continue;
+            }
+            "B" => Piece::Black,
+            "W" => Piece::White,
+            _ => continue,
+        };
+        moves += 1;
+        // Moves may be followed by an evaluation and the time taken, as in `f5/1.23/0.01`.
+        let square = value.split('/').next().unwrap_or_default().trim();
+        if square.eq_ignore_ascii_case("pa") {
+            // Passes are made automatically, so only passes that haven't been made yet
+            // need playing.
+            if game.turn() == color {
+                game.pass().map_err(|e| GgfError::Move(number, moves, e))?;
+            }
+            continue;
+        }
+        let (x, y) =
+            notation::square(square).ok_or_else(|| GgfError::Square(number, square.into()))?;
+        game.place(x, y, color)
+            .map_err(|e| GgfError::Move(number, moves, e))?;
+    }
+    let result = result
+        .map(|value| parse_result(&game, value).ok_or(GgfError::Result(number, value.clone())))
+        .transpose()?;
+    Ok(Record {
+        black,
+        white,
+        game,
+        result,
+    })
+}
+
+/// Parses a result such as `+18.000`, or `-64:r` for a game that Black resigned. A game that
+/// wasn't played out but was drawn anyway is taken to be a draw by agreement.
+fn parse_result(game: &Game, value: &str) -> Option<GameResult> {
+    let (margin, reason) = value.split_once(':').unwrap_or((value, ""));
+    let margin: f64 = margin.trim().parse().ok()?;
+    let reason = match reason {
+        "" if margin == 0.0 && game.result().is_none() => Reason::Agreement,
+        "" => game
+            .result()
+            .map_or(Reason::Resignation, |result| result.reason),
+        "r" => Reason::Resignation,
+        "t" => Reason::Timeout,
+        _ => return None,
+    };
+    let winner = if margin > 0.0 {
+        Some(Piece::Black)
+    } else if margin < 0.0 {
+        Some(Piece::White)
+    } else {
+        None
+    };
+    Some(GameResult {
+        winner,
+        score: game.score(),
+        reason,
+    })
+}
+
+/// Black's margin of victory in `result`. Games that were lost on resignation or time are
+/// given the widest possible margin on a board `width` squares wide, and agreed draws none at
+/// all.
+fn margin(result: GameResult, width: usize) -> String {
+    let (black, white) = result.score;
+    let all = width * width;
+    let (black, white, suffix) = match (result.reason, result.winner) {
+        (Reason::Resignation | Reason::Timeout, Some(Piece::Black)) => (all, 0, result.reason),
+        (Reason::Resignation | Reason::Timeout, Some(Piece::White)) => (0, all, result.reason),
+        (Reason::Agreement, _) => (0, 0, result.reason),
+        (reason, _) => (black, white, reason),
+    };
+    let sign = if black >= white { '+' } else { '-' };
+    let suffix = match suffix {
+        Reason::Resignation => ":r",
+        Reason::Timeout => ":t",
+        _ => "",
+    };
+    format!("{sign}{}.000{suffix}", black.abs_diff(white))
+}
+
+/// The position in `game` as a `BO` property: the board size, each row from the top with
+/// `*` for black, `O` for white and `-` for empty squares, then the color to move.
+fn board(game: &Game) -> String {
+    let width = game.width();
+    let mut text = width.to_string();
+    for y in 0..width {
+        text.push(' ');
+        text.extend((0..width).map(|x| match game.board()[(x, y)] {
+            Some(Piece::Black) => '*',
+            Some(Piece::White) => 'O',
+            None => '-',
+        }));
+    }
+    text.push(' ');
+    text.push(match game.turn() {
+        Piece::Black => '*',
+        Piece::White => 'O',
+    });
+    text
+}
+
+/// Parses a `BO` property into a game that starts from its position.
+fn parse_board(number: usize, value: &str) -> Result<Game, GgfError> {
+    let mut tokens: Vec<&str> = value.split_whitespace().collect();
+    let Some(width) = tokens
+        .first()
+        .and_then(|width| width.parse::<usize>().ok())
+        .filter(|width| WIDTHS.contains(width))
+    else {
+        return Err(GgfError::Unsupported(number, value.to_string()));
+    };
+    let side = match tokens.pop() {
+        Some("*") => Piece::Black,
+        Some("O") => Piece::White,
+        side => {
+            let side = side.unwrap_or_default().to_string();
+            return Err(GgfError::Position(number, PositionError::Side(side)));
+        }
+    };
+    let squares = tokens[1..].concat().replace('*', "X");
+    let game = Game::from_position(&squares, side).map_err(|e| GgfError::Position(number, e))?;
+    if game.width() != width {
+        return Err(GgfError::Position(
+            number,
+            PositionError::Length(squares.len()),
+        ));
+    }
+    Ok(game)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write, GgfError};
+    use crate::{Game, Piece, PlaceError, PositionError, Reason, Record};
+
+    const SAMPLE: &str = include_str!("../data/sample.ggf");
+
+    #[test]
+    fn sample() {
+        let records = read(SAMPLE).unwrap();
+        assert_eq!(records.len(), 3);
+        let first = &records[0];
+        assert_eq!(
+            (first.black.as_str(), first.white.as_str()),
+            ("Saio1200", "Saio3000")
+        );
+        assert!(first.game.over());
+        assert_eq!(first.game.score(), (43, 21));
+        let result = first.result.unwrap();
+        assert_eq!(result.winner, Some(Piece::Black));
+        assert_eq!(result.reason, Reason::BoardFull);
+        // Names can contain escaped brackets, and moves can be in either case.
+        assert_eq!(records[1].white, "Edax]");
+        assert_eq!(records[1].result.unwrap().winner, Some(Piece::White));
+        assert_eq!(records[1].game.score(), (19, 45));
+        let last = &records[2];
+        assert_eq!(last.game, Game::from_transcript("f5d6c3d3c4").unwrap());
+        let result = last.result.unwrap();
+        assert_eq!(result.winner, Some(Piece::White));
+        assert_eq!(result.reason, Reason::Resignation);
+    }
+
+    #[test]
+    fn round_trip() {
+        for record in read(SAMPLE).unwrap() {
+            let written = write(&record);
+            assert_eq!(read(&written).unwrap(), vec![record]);
+        }
+        // Agreed draws are written as a draw, whatever the discs on the board.
+        let game = Game::from_transcript("f5f6e6").unwrap();
+        let record = Record {
+            black: "alice".to_string(),
+            white: "bob".to_string(),
+            result: Some(game.forfeit(Reason::Agreement, None)),
+            game,
+        };
+        let written = write(&record);
+        assert!(written.contains("RE[+0.000]"));
+        assert_eq!(read(&written).unwrap(), vec![record]);
+    }
+
+    #[test]
+    fn positions() {
+        // Black to move with only the last two squares of the bottom row empty.
+        let rows = ["O*******", "********", "********", "********"];
+        let bottom = ["********", "********", "********", "*****O--"];
+        let text = format!("(;BO[8 {} {} *]B[g8];)", rows.join(" "), bottom.join(" "));
+        let records = read(&text).unwrap();
+        let game = &records[0].game;
+        assert_eq!(game.history(), vec![Some((6, 7))]);
+        assert_eq!(game.start().score(), (60, 2));
+        assert_eq!(read(&write(&records[0])).unwrap(), records);
+        // Other board sizes start from their own center position.
+        let records = read("(;GM[Othello]TY[10]B[e4];)").unwrap();
+        let game = &records[0].game;
+        assert_eq!(game.width(), 10);
+        assert_eq!(game.history(), vec![Some((4, 3))]);
+        assert_eq!(read(&write(&records[0])).unwrap(), records);
+        assert_eq!(
+            read("(;BO[8 ---- *];)").unwrap_err(),
+            GgfError::Position(1, PositionError::Length(4))
+        );
+        assert_eq!(
+            read("(;B[f5]BO[8 -------- *];)").unwrap_err(),
+            GgfError::Syntax(1, "the board before any moves")
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            read("(;GM[Chess];)").unwrap_err(),
+            GgfError::Unsupported(1, "Chess".to_string())
+        );
+        assert_eq!(
+            read("(;BO[12 *];)").unwrap_err(),
+            GgfError::Unsupported(1, "12 *".to_string())
+        );
+        assert_eq!(
+            read("(;TY[12];)").unwrap_err(),
+            GgfError::Unsupported(1, "12".to_string())
+        );
+        assert_eq!(
+            read("(;B[f5]TY[10];)").unwrap_err(),
+            GgfError::Unsupported(1, "10".to_string())
+        );
+        assert_eq!(
+            read("(;GM[Othello];)\n(;B[f5]W[f5];)").unwrap_err(),
+            GgfError::Move(2, 2, PlaceError::Occupied(5, 4))
+        );
+        assert_eq!(
+            read("(;B[z9];)").unwrap_err(),
+            GgfError::Square(1, "z9".to_string())
+        );
+        assert_eq!(
+            read("(;RE[?];)").unwrap_err(),
+            GgfError::Result(1, "?".to_string())
+        );
+        assert_eq!(
+            read("(;PB[alice").unwrap_err(),
+            GgfError::Syntax(1, "the end of a property")
+        );
+        assert_eq!(
+            read("(;pb[alice];)").unwrap_err(),
+            GgfError::Syntax(1, "a property or the end of the record")
+        );
+    }
+}
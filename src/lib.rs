@@ -1,12 +1,30 @@
-pub use board::Piece;
-pub use game::Game;
+pub use board::{Bitboard, Board, Piece, WIDTHS};
+pub use book::{Book, BookError};
+pub use companion::{
+    Analysis, Companion, ScoredMove, Solution, Solve, Suggestion, ENDGAME_EMPTIES,
+};
+pub use difficulty::{Difficulty, Settings};
+pub use evaluation::{DiscCount, Evaluator, Positional, Weights};
+pub use game::{Game, Step};
+pub use notation::NotationError;
+pub use record::Record;
+pub use result::{GameResult, Outcome, Reason};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+pub use transposition::TranspositionTable;
 
 mod board;
+mod book;
 mod companion;
+mod difficulty;
+mod evaluation;
 mod game;
+pub mod ggf;
+pub mod notation;
+mod record;
+mod result;
 pub mod server;
+mod transposition;
+pub mod wthor;
 
 #[derive(thiserror::Error, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PlaceError {
@@ -20,11 +38,20 @@ pub enum PlaceError {
     OutOfBounds(usize, usize),
     #[error("no pieces were flipped from board square ({0}, {1})")]
     NoFlips(usize, usize),
+    #[error("{0:?} cannot pass while a legal move is available or the game is over")]
+    Pass(Piece),
 }
 
-fn convert<T, R: TryFrom<T>>(x: T, y: T) -> (R, R)
-where
-    R::Error: fmt::Debug,
-{
-    (R::try_from(x).unwrap(), R::try_from(y).unwrap())
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum PositionError {
+    #[error("expected 36, 64 or 100 squares, but found {0}")]
+    Length(usize),
+    #[error("{0}x{0} boards are not supported")]
+    Width(usize),
+    #[error("square {0} is {1:?} rather than X, O or -")]
+    Square(String, char),
+    #[error("the four center squares must all be occupied")]
+    Center,
+    #[error("{0:?} is not a side to move, which is either X or O")]
+    Side(String),
 }
@@ -0,0 +1,114 @@
+use crate::{PlaceError, WIDTHS};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum NotationError {
+    #[error("{0:?} is not a square in coordinate notation")]
+    Square(String),
+    #[error("move {0}: {1}")]
+    Move(usize, PlaceError),
+}
+
+/// The width of the largest board, which bounds the squares that can be written down.
+const MAX_WIDTH: usize = WIDTHS[WIDTHS.len() - 1];
+
+/// Parses a square in coordinate notation, where columns are lettered from `a` on the left
+/// and rows are numbered from `1` at the top. Letters may be either case. Squares up to `j10`
+/// are accepted so that larger boards can be written down; whether a square is on the board
+/// of a particular game is up to the game.
+#[must_use]
+pub fn square(text: &str) -> Option<(usize, usize)> {
+    let mut chars = text.chars();
+    let x = chars.next()?.to_ascii_lowercase();
+    let rest = chars.as_str();
+    if !x.is_ascii_lowercase() || rest.starts_with('0') || !rest.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let (x, y) = (
+        x as usize - 'a' as usize,
+        rest.parse::<usize>().ok()?.checked_sub(1)?,
+    );
+    (x < MAX_WIDTH && y < MAX_WIDTH).then_some((x, y))
+}
+
+/// Writes (x, y) in coordinate notation, the inverse of [`square`].
+///
+/// # Panics
+/// Panics if the square is off the largest board.
+#[must_use]
+pub fn name((x, y): (usize, usize)) -> String {
+    assert!(
+        x < MAX_WIDTH && y < MAX_WIDTH,
+        "({x}, {y}) is off the board"
+    );
+    let column = char::from(b'a' + u8::try_from(x).expect("checked above"));
+    format!("{column}{}", y + 1)
+}
+
+/// Parses a transcript, which is a sequence of squares in coordinate notation such as
+/// `f5d6c3d3c4`. Whitespace between squares is ignored, and passes are left out. Each square
+/// is a letter followed by every digit up to the next letter, so `a10` is a single square.
+///
+/// # Errors
+/// Returns an error for the first chunk of the transcript that isn't a square.
+pub fn squares(transcript: &str) -> Result<Vec<(usize, usize)>, NotationError> {
+    let compact: String = transcript.split_whitespace().collect();
+    let mut chunks: Vec<String> = vec![];
+    for c in compact.chars() {
+        match chunks.last_mut() {
+            Some(chunk) if c.is_ascii_digit() => chunk.push(c),
+            _ => chunks.push(c.to_string()),
+        }
+    }
+    chunks
+        .into_iter()
+        .map(|text| square(&text).ok_or(NotationError::Square(text)))
+        .collect()
+}
+
+/// Writes `squares` as a compact transcript, the inverse of [`squares`].
+#[must_use]
+pub fn transcript(squares: &[(usize, usize)]) -> String {
+    squares.iter().map(|&square| name(square)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{name, square, squares, transcript, NotationError};
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(square("a1"), Some((0, 0)));
+        assert_eq!(square("F5"), Some((5, 4)));
+        assert_eq!(square("h8"), Some((7, 7)));
+        assert_eq!(square("j10"), Some((9, 9)));
+        assert_eq!(square("k1"), None);
+        assert_eq!(square("a11"), None);
+        assert_eq!(square("a0"), None);
+        assert_eq!(square("a01"), None);
+        assert_eq!(square("a"), None);
+        for x in 0..10 {
+            for y in 0..10 {
+                assert_eq!(square(&name((x, y))), Some((x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn transcripts() {
+        let moves = squares("f5d6 C3").unwrap();
+        assert_eq!(moves, vec![(5, 4), (3, 5), (2, 2)]);
+        assert_eq!(transcript(&moves), "f5d6c3");
+        let moves = squares("a10j1b9").unwrap();
+        assert_eq!(moves, vec![(0, 9), (9, 0), (1, 8)]);
+        assert_eq!(transcript(&moves), "a10j1b9");
+        assert_eq!(
+            squares("f5z9").unwrap_err(),
+            NotationError::Square("z9".to_string())
+        );
+        assert_eq!(
+            squares("f5d").unwrap_err(),
+            NotationError::Square("d".to_string())
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use crate::{Game, GameResult};
+
+/// A game as stored in the formats used by other Othello software, along with who played
+/// it. See the [`ggf`](crate::ggf) and [`wthor`](crate::wthor) modules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// The name of the player with the black discs.
+    pub black: String,
+    /// The name of the player with the white discs.
+    pub white: String,
+    pub game: Game,
+    /// How the game ended, or `None` if the record doesn't say.
+    pub result: Option<GameResult>,
+}
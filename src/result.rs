@@ -0,0 +1,65 @@
+use crate::Piece;
+use serde::{Deserialize, Serialize};
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// Every square was filled.
+    BoardFull,
+    /// Neither player could move, though some squares were still empty.
+    NoMoves,
+    /// A player gave up.
+    Resignation,
+    /// The players agreed to a draw.
+    Agreement,
+    /// A player ran out of time.
+    Timeout,
+}
+
+/// How a finished game went for one of its players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// The result of a finished game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResult {
+    /// The color that won, or `None` for a draw.
+    pub winner: Option<Piece>,
+    /// The number of black and white discs on the board when the game ended.
+    pub score: (usize, usize),
+    pub reason: Reason,
+}
+
+impl GameResult {
+    /// The result of a game that was played out, which is won by whoever has more discs.
+    #[must_use]
+    pub fn played(score: (usize, usize), reason: Reason) -> Self {
+        let (black, white) = score;
+        let winner = match black.cmp(&white) {
+            std::cmp::Ordering::Greater => Some(Piece::Black),
+            std::cmp::Ordering::Less => Some(Piece::White),
+            std::cmp::Ordering::Equal => None,
+        };
+        Self {
+            winner,
+            score,
+            reason,
+        }
+    }
+
+    /// How the game went for the player of `piece`.
+    #[must_use]
+    pub fn outcome(&self, piece: Piece) -> Outcome {
+        match self.winner {
+            Some(winner) if winner == piece => Outcome::Win,
+            Some(_) => Outcome::Loss,
+            None => Outcome::Draw,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    server::{entities::game, helpers, packet, state::AppState},
+    Companion, Difficulty, Game, TranspositionTable,
+};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::{uuid, Uuid};
+
+/// The member that plays the companion's moves in games against the computer. Created by a
+/// migration, without a password so that nobody can log in as it.
+pub const BOT_ID: Uuid = uuid!("00000000-0000-7000-8000-000000000001");
+
+/// The difficulty the bot plays at in the game described by `metadata`, or `None` if the
+/// game is between two players.
+pub fn difficulty(metadata: &game::Model) -> Option<Difficulty> {
+    helpers::parse_column(metadata.bot.as_deref()?)
+}
+
+/// Plays the bot's moves in the game described by `metadata` until the game ends or is
+/// abandoned. The bot checks the game whenever its room is updated, and moves when it's the
+/// bot's turn.
+pub fn spawn(state: Arc<AppState>, metadata: game::Model, difficulty: Difficulty) {
+    tokio::spawn(async move {
+        let rx = {
+            let rooms = state.rooms.lock().expect("mutex was poisoned");
+            rooms
+                .get(&metadata.id)
+                .map(tokio::sync::broadcast::Sender::subscribe)
+        };
+        let Some(mut rx) = rx else {
+            return;
+        };
+        // Keep search results between moves, since later searches revisit many of the same
+        // positions.
+        let mut table = TranspositionTable::default();
+        let piece = helpers::piece(&metadata, &BOT_ID.to_string()).expect("the bot is the guest");
+        loop {
+            let game = {
+                let games = state.games.lock().expect("mutex was poisoned");
+                games.get(&metadata.id).cloned()
+            };
+            // The game was abandoned or is over, so there's nothing left to play.
+            let Some(game) = game.filter(|game| !game.over()) else {
+                return;
+            };
+            if game.turn() == piece {
+                let history = game.history();
+                let ((x, y), returned) = think(&state, game, difficulty, table).await;
+                table = returned;
+                // The opponent may have taken a move back while the bot was thinking, in
+                // which case the move was for a position that is gone.
+                let current = {
+                    let games = state.games.lock().expect("mutex was poisoned");
+                    games.get(&metadata.id).map(Game::history)
+                };
+                if current.is_some_and(|current| current != history) {
+                    continue;
+                }
+                if let Err(e) = packet::play(&state, &metadata, x, y, piece).await {
+                    log::error!("bot failed to move in {}: {e:?}", metadata.id);
+                    return;
+                }
+                // Look at the game again rather than waiting, in case the opponent had to
+                // pass.
+                continue;
+            }
+            if let Err(RecvError::Closed) = rx.recv().await {
+                return;
+            }
+        }
+    });
+}
+
+/// Finds the bot's move in `game`. The search is CPU-bound, so it runs off of the async
+/// worker threads; the table is moved there and back so it can be reused for the next move.
+async fn think(
+    state: &AppState,
+    game: Game,
+    difficulty: Difficulty,
+    mut table: TranspositionTable,
+) -> ((usize, usize), TranspositionTable) {
+    let book = Arc::clone(&state.book);
+    let settings = difficulty.settings();
+    tokio::task::spawn_blocking(move || {
+        let suggestion = Companion::from(&game)
+            .with_table(&mut table)
+            .with_settings(&settings, &book)
+            .suggest(settings.depth, settings.budget);
+        (suggestion.square, table)
+    })
+    .await
+    .expect("the companion's search panicked")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BOT_ID;
+    use crate::{
+        server::{self, helpers, packet},
+        Piece,
+    };
+    use std::{sync::Arc, time::Duration};
+    use test_utils::{function, Client, Map};
+
+    #[tokio::test]
+    async fn play() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 201);
+        let id = res["message"]["id"].as_str().unwrap();
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        assert_eq!(metadata.guest, BOT_ID.to_string());
+        assert_eq!(metadata.bot.as_deref(), Some("beginner"));
+        assert!(!metadata.pending);
+        // The host plays Black and moves first, then the bot replies through the same path.
+        packet::play(&state, &metadata, 5, 4, Piece::Black)
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            let game = state.games.lock().unwrap()[&metadata.id].clone();
+            if game.turn() == Piece::Black {
+                assert_eq!(game.history().len(), 2);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("the bot never moved");
+    }
+
+    #[tokio::test]
+    async fn bot_black() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "white" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 201);
+        assert_eq!(res["message"]["black"], BOT_ID.to_string());
+        let id = res["message"]["id"].as_str().unwrap();
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        assert_eq!(
+            helpers::piece(&metadata, &metadata.host),
+            Some(Piece::White)
+        );
+        // The bot plays Black, so it opens the game without waiting for the host.
+        for _ in 0..100 {
+            let game = state.games.lock().unwrap()[&metadata.id].clone();
+            if game.turn() == Piece::White {
+                assert_eq!(game.history().len(), 1);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("the bot never moved");
+    }
+
+    #[tokio::test]
+    async fn opponent() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(state)).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        // Either another player or the bot, but not both or neither.
+        for body in [
+            serde_json::json!({}),
+            serde_json::json!({ "guest": "olly" }),
+            serde_json::json!({ "guest": function!(), "bot": "expert" }),
+        ] {
+            let res: Map = client.post(&url, "/game", &body).await;
+            assert_eq!(res["code"], 400);
+        }
+    }
+}
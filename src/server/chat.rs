@@ -0,0 +1,141 @@
+use crate::{
+    server::{
+        entities::{chat_message, prelude::ChatMessage},
+        handlers::StringError,
+        helpers,
+        state::AppState,
+        strings,
+    },
+    Piece,
+};
+use axum::http::StatusCode;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// The longest message that can be sent in a game's chat, in characters.
+pub const MAX_LENGTH: usize = 500;
+/// How many messages a player can send in a game's chat within [`WINDOW`].
+const BURST: usize = 5;
+const WINDOW: Duration = Duration::from_secs(10);
+
+/// `message` without its surrounding whitespace, as long as something is left and it isn't
+/// longer than [`MAX_LENGTH`].
+pub fn validate(message: &str) -> Result<&str, StringError> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err(StringError(
+            strings::CHAT_EMPTY.into(),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if message.chars().count() > MAX_LENGTH {
+        return Err(StringError(
+            strings::CHAT_TOO_LONG.into(),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    Ok(message)
+}
+
+/// Counts a message from the player of `piece` in the game with ID `uuid` against their rate
+/// limit. Returns `false` without counting it if they've already sent [`BURST`] messages
+/// within the last [`WINDOW`].
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn allow(state: &AppState, uuid: Uuid, piece: Piece) -> bool {
+    let mut chats = state.chats.lock().expect("mutex was poisoned");
+    let sent = chats.entry((uuid, piece)).or_default();
+    let now = Instant::now();
+    while sent.front().is_some_and(|&at| now - at >= WINDOW) {
+        sent.pop_front();
+    }
+    if sent.len() >= BURST {
+        return false;
+    }
+    sent.push_back(now);
+    true
+}
+
+/// Saves `message`, sent by the member with ID `author` playing `piece`, to the chat of the
+/// game with ID `uuid`.
+pub async fn record(
+    state: &AppState,
+    uuid: Uuid,
+    author: &str,
+    piece: Piece,
+    message: &str,
+) -> Result<chat_message::Model, StringError> {
+    let model = chat_message::ActiveModel {
+        id: ActiveValue::set(Uuid::now_v7()),
+        game_id: ActiveValue::set(uuid),
+        author: ActiveValue::set(author.to_string()),
+        color: ActiveValue::set(helpers::column(piece).unwrap_or_default()),
+        message: ActiveValue::set(message.to_string()),
+        sent_at: ActiveValue::NotSet,
+    };
+    model
+        .insert(state.database.as_ref())
+        .await
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Every message sent in the chat of the game with ID `uuid`, oldest first.
+pub async fn history(
+    state: &AppState,
+    uuid: Uuid,
+) -> Result<Vec<chat_message::Model>, StringError> {
+    ChatMessage::find()
+        .filter(chat_message::Column::GameId.eq(uuid))
+        .order_by_asc(chat_message::Column::SentAt)
+        .order_by_asc(chat_message::Column::Id)
+        .all(state.database.as_ref())
+        .await
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Stops the player of `piece` from receiving their opponent's messages in the game with ID
+/// `uuid` until it's over.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn mute(state: &AppState, uuid: Uuid, piece: Piece) {
+    state
+        .mutes
+        .lock()
+        .expect("mutex was poisoned")
+        .entry(uuid)
+        .or_default()
+        .insert(piece);
+}
+
+/// Whether the player of `viewer` has muted their opponent in the game with ID `uuid`.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn muted(mutes: &Mutex<HashMap<Uuid, HashSet<Piece>>>, uuid: Uuid, viewer: Piece) -> bool {
+    let mutes = mutes.lock().expect("mutex was poisoned");
+    mutes
+        .get(&uuid)
+        .is_some_and(|muted| muted.contains(&viewer))
+}
+
+/// Forgets the mutes and rate limits of the game with ID `uuid` once it's out of play. Its
+/// messages stay in the database.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn forget(state: &AppState, uuid: Uuid) {
+    state
+        .mutes
+        .lock()
+        .expect("mutex was poisoned")
+        .remove(&uuid);
+    state
+        .chats
+        .lock()
+        .expect("mutex was poisoned")
+        .retain(|&(game, _), _| game != uuid);
+}
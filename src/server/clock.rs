@@ -0,0 +1,304 @@
+use crate::{
+    server::{entities::game, packet, state::AppState},
+    Piece, Reason,
+};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::broadcast::error::RecvError, time::Instant};
+use uuid::Uuid;
+
+/// How much time each player gets to make their moves, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimeControl {
+    /// `base` seconds for the whole game, plus `increment` seconds after each move.
+    Increment {
+        base: u32,
+        #[serde(default)]
+        increment: u32,
+    },
+    /// `seconds` for every move, with nothing carried over to the next one.
+    PerMove { seconds: u32 },
+}
+
+impl TimeControl {
+    /// The time control that the game described by `metadata` was created with, if any.
+    pub fn of(metadata: &game::Model) -> Option<Self> {
+        let seconds = |column: Option<i32>| column.and_then(|n| u32::try_from(n).ok());
+        match (metadata.time_base, metadata.time_per_move) {
+            (Some(base), _) => Some(Self::Increment {
+                base: seconds(Some(base))?,
+                increment: seconds(metadata.time_increment).unwrap_or_default(),
+            }),
+            (None, Some(per_move)) => Some(Self::PerMove {
+                seconds: seconds(Some(per_move))?,
+            }),
+            (None, None) => None,
+        }
+    }
+
+    /// How the time control is stored in the base, increment and per-move columns, or `None`
+    /// if it gives players no time or more than the columns can hold.
+    pub fn columns(self) -> Option<(Option<i32>, Option<i32>, Option<i32>)> {
+        let seconds = |n: u32| i32::try_from(n).ok();
+        match self {
+            Self::Increment { base, increment } if base > 0 => {
+                Some((Some(seconds(base)?), Some(seconds(increment)?), None))
+            }
+            Self::PerMove { seconds: per_move } if per_move > 0 => {
+                Some((None, None, Some(seconds(per_move)?)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The time a player starts the game with.
+    fn initial(self) -> Duration {
+        match self {
+            Self::Increment { base, .. } => Duration::from_secs(base.into()),
+            Self::PerMove { seconds } => Duration::from_secs(seconds.into()),
+        }
+    }
+}
+
+/// The time each player has left in a game, counting down for whoever's turn it is.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    black: Duration,
+    white: Duration,
+    turn: Piece,
+    /// When the player whose turn it is started thinking.
+    since: Instant,
+}
+
+/// A snapshot of a [`Clock`], as sent to players with every update to the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Times {
+    /// Milliseconds left on Black's clock.
+    pub black: u64,
+    /// Milliseconds left on White's clock.
+    pub white: u64,
+    /// Whose clock is running.
+    pub turn: Piece,
+}
+
+impl Clock {
+    /// A clock with a full allowance for both players, running for `turn`.
+    pub fn new(control: TimeControl, turn: Piece) -> Self {
+        Self {
+            control,
+            black: control.initial(),
+            white: control.initial(),
+            turn,
+            since: Instant::now(),
+        }
+    }
+
+    /// The time `piece` has left, including the time spent on the current move.
+    pub fn remaining(&self, piece: Piece) -> Duration {
+        let left = match piece {
+            Piece::Black => self.black,
+            Piece::White => self.white,
+        };
+        if piece == self.turn {
+            left.saturating_sub(self.since.elapsed())
+        } else {
+            left
+        }
+    }
+
+    /// The player whose time has run out, if any.
+    pub fn flagged(&self) -> Option<Piece> {
+        self.remaining(self.turn).is_zero().then_some(self.turn)
+    }
+
+    /// Stops the running clock and starts `turn`'s. After a move, the player who made it
+    /// gains their increment. With a time limit per move, the time left over is thrown away
+    /// instead, so every move starts with a fresh allowance.
+    pub fn switch(&mut self, turn: Piece, moved: bool) {
+        let left = match (self.control, moved) {
+            (TimeControl::PerMove { .. }, _) => self.control.initial(),
+            (TimeControl::Increment { increment, .. }, true) => {
+                self.remaining(self.turn) + Duration::from_secs(increment.into())
+            }
+            (TimeControl::Increment { .. }, false) => self.remaining(self.turn),
+        };
+        *self.allowance(self.turn) = left;
+        self.turn = turn;
+        self.since = Instant::now();
+    }
+
+    pub fn times(&self) -> Times {
+        let millis = |piece| u64::try_from(self.remaining(piece).as_millis()).unwrap_or(u64::MAX);
+        Times {
+            black: millis(Piece::Black),
+            white: millis(Piece::White),
+            turn: self.turn,
+        }
+    }
+
+    fn allowance(&mut self, piece: Piece) -> &mut Duration {
+        match piece {
+            Piece::Black => &mut self.black,
+            Piece::White => &mut self.white,
+        }
+    }
+}
+
+/// Starts the clock of the game described by `metadata`, if it has a time control, for
+/// whoever is to move in it. Clocks aren't saved, so a game restored after a restart starts
+/// over with a full allowance for both players.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn start(state: &Arc<AppState>, metadata: &game::Model, turn: Piece) {
+    let Some(control) = TimeControl::of(metadata) else {
+        return;
+    };
+    state
+        .clocks
+        .lock()
+        .expect("mutex was poisoned")
+        .insert(metadata.id, Clock::new(control, turn));
+    tokio::spawn(watch(Arc::clone(state), metadata.clone()));
+}
+
+/// Takes the clock of the game with ID `uuid` out of play if its running player's time has
+/// run out, returning that player. Only the first caller to notice gets the player, so the
+/// game is only ended once.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn expire(state: &AppState, uuid: Uuid) -> Option<Piece> {
+    let mut clocks = state.clocks.lock().expect("mutex was poisoned");
+    let loser = clocks.get(&uuid)?.flagged()?;
+    clocks.remove(&uuid);
+    Some(loser)
+}
+
+/// Hands the clock of the game with ID `uuid`, if it has one, to `turn`. See [`Clock::switch`].
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn press(state: &AppState, uuid: Uuid, turn: Piece, moved: bool) {
+    if let Some(clock) = state
+        .clocks
+        .lock()
+        .expect("mutex was poisoned")
+        .get_mut(&uuid)
+    {
+        clock.switch(turn, moved);
+    }
+}
+
+/// The time left on the clock of the game with ID `uuid`, if it has one.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn times(state: &AppState, uuid: Uuid) -> Option<Times> {
+    let clocks = state.clocks.lock().expect("mutex was poisoned");
+    clocks.get(&uuid).map(Clock::times)
+}
+
+/// Ends the game described by `metadata` when a player's time runs out, even if nobody sends
+/// anything. The timer is reset whenever the game's room is updated, since a move or takeback
+/// starts the other player's clock.
+async fn watch(state: Arc<AppState>, metadata: game::Model) {
+    let rx = {
+        let rooms = state.rooms.lock().expect("mutex was poisoned");
+        rooms
+            .get(&metadata.id)
+            .map(tokio::sync::broadcast::Sender::subscribe)
+    };
+    let Some(mut rx) = rx else {
+        return;
+    };
+    loop {
+        let left = {
+            let clocks = state.clocks.lock().expect("mutex was poisoned");
+            // The game ended some other way, so its clock was stopped.
+            let Some(clock) = clocks.get(&metadata.id) else {
+                return;
+            };
+            clock.remaining(clock.turn)
+        };
+        if left.is_zero() {
+            break;
+        }
+        if let Ok(Err(RecvError::Closed)) = tokio::time::timeout(left, rx.recv()).await {
+            return;
+        }
+    }
+    let Some(loser) = expire(&state, metadata.id) else {
+        return;
+    };
+    let game = {
+        let games = state.games.lock().expect("mutex was poisoned");
+        games.get(&metadata.id).cloned()
+    };
+    let Some(game) = game else {
+        return;
+    };
+    let result = game.forfeit(Reason::Timeout, Some(loser));
+    if let Err(e) = packet::conclude(&state, &metadata, result).await {
+        log::error!("failed to end {} on time: {e:?}", metadata.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, TimeControl};
+    use crate::Piece;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn increment() {
+        let control = TimeControl::Increment {
+            base: 10,
+            increment: 2,
+        };
+        let mut clock = Clock::new(control, Piece::Black);
+        tokio::time::advance(Duration::from_secs(3)).await;
+        assert_eq!(clock.remaining(Piece::Black), Duration::from_secs(7));
+        assert_eq!(clock.remaining(Piece::White), Duration::from_secs(10));
+        // Black gains the increment for moving, and White's clock starts.
+        clock.switch(Piece::White, true);
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let times = clock.times();
+        assert_eq!((times.black, times.white), (9_000, 9_000));
+        assert_eq!(times.turn, Piece::White);
+        // Taking a move back isn't a move, so it doesn't earn an increment.
+        clock.switch(Piece::Black, false);
+        assert_eq!(clock.remaining(Piece::White), Duration::from_secs(9));
+        tokio::time::advance(Duration::from_secs(9)).await;
+        assert_eq!(clock.flagged(), Some(Piece::Black));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn per_move() {
+        let mut clock = Clock::new(TimeControl::PerMove { seconds: 5 }, Piece::Black);
+        tokio::time::advance(Duration::from_secs(4)).await;
+        clock.switch(Piece::White, true);
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert_eq!(clock.flagged(), None);
+        // The time left over from a move isn't carried over to the next one.
+        clock.switch(Piece::Black, true);
+        assert_eq!(clock.remaining(Piece::Black), Duration::from_secs(5));
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(clock.flagged(), Some(Piece::Black));
+    }
+
+    #[test]
+    fn columns() {
+        let control = TimeControl::Increment {
+            base: 300,
+            increment: 5,
+        };
+        assert_eq!(control.columns(), Some((Some(300), Some(5), None)));
+        let control = TimeControl::PerMove { seconds: 30 };
+        assert_eq!(control.columns(), Some((None, None, Some(30))));
+        assert_eq!(TimeControl::PerMove { seconds: 0 }.columns(), None);
+        let control = TimeControl::Increment {
+            base: u32::MAX,
+            increment: 0,
+        };
+        assert_eq!(control.columns(), None);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::server::{
+    entities::{
+        game::{self, Column},
+        prelude::Game as GameModel,
+    },
+    handlers::StringError,
+    helpers, packet,
+    state::AppState,
+};
+use crate::Reason;
+use axum::http::StatusCode;
+use sea_orm::{sea_query::Expr, ColumnTrait, EntityTrait, QueryFilter};
+use std::{sync::Arc, time::Duration};
+
+/// How often games are checked for missed deadlines. Deadlines are days apart, so being a
+/// minute late to notice one doesn't matter.
+const INTERVAL: Duration = Duration::from_mins(1);
+
+/// Forfeits correspondence games whose deadlines have passed, in the background, for as long
+/// as the server runs.
+pub fn spawn(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(StringError(message, _)) = forfeit_overdue(&state).await {
+                log::error!("failed to forfeit overdue games: {message}");
+            }
+        }
+    });
+}
+
+/// Ends every game whose deadline has passed with a loss on time for the player who was to
+/// move, returning how many were ended.
+pub async fn forfeit_overdue(state: &AppState) -> Result<usize, StringError> {
+    let overdue = GameModel::find()
+        .filter(Column::Ended.eq(false))
+        .filter(Column::Pending.eq(false))
+        .filter(Expr::col(Column::Deadline).lt(Expr::current_timestamp()))
+        .all(state.database.as_ref())
+        .await
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok(forfeit(state, &overdue).await)
+}
+
+/// Ends each of `games` with a loss on time for the player who was to move, returning how many
+/// were ended. A game that can't be ended is logged and skipped, so that it doesn't hold up
+/// the others.
+async fn forfeit(state: &AppState, games: &[game::Model]) -> usize {
+    let mut ended = 0;
+    for metadata in games {
+        let game = {
+            let games = state.games.lock().expect("mutex was poisoned");
+            games.get(&metadata.id).cloned()
+        };
+        let game = match game {
+            Some(game) => game,
+            None => match helpers::replay(state, metadata).await {
+                Ok(game) => game,
+                Err(StringError(message, _)) => {
+                    log::error!("failed to replay overdue game {}: {message}", metadata.id);
+                    continue;
+                }
+            },
+        };
+        let result = game.forfeit(Reason::Timeout, Some(game.turn()));
+        match packet::conclude(state, metadata, result).await {
+            Ok(()) => ended += 1,
+            Err(e) => {
+                let StringError(message, _) = e.into();
+                log::error!("failed to forfeit overdue game {}: {message}", metadata.id);
+            }
+        }
+    }
+    ended
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        server::{self, helpers},
+        Piece,
+    };
+    use std::sync::Arc;
+    use test_utils::{function, Client, Map};
+
+    #[tokio::test]
+    async fn broken() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let mut games = vec![];
+        for _ in 0..2 {
+            let body = serde_json::json!({ "guest": guest, "color": "black", "days_per_move": 3 });
+            let res: Map = client.post(&url, "/game", &body).await;
+            let id = res["message"]["id"].as_str().unwrap().to_string();
+            let endpoint = format!("/@me/games/{id}/accept");
+            let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+            games.push(helpers::get_game(&state, &id).await.unwrap());
+        }
+        // The first game has an illegal move on record and isn't in memory, so it can't be
+        // rebuilt.
+        let broken = games[0].id;
+        state.games.lock().unwrap().remove(&broken);
+        helpers::record_move(&state, broken, 0, (0, 0), Piece::Black, 0)
+            .await
+            .unwrap();
+        // It's skipped, and the game after it still ends.
+        assert_eq!(super::forfeit(&state, &games).await, 1);
+        assert!(
+            !helpers::get_game(&state, &broken.to_string())
+                .await
+                .unwrap()
+                .ended
+        );
+        let metadata = helpers::get_game(&state, &games[1].id.to_string())
+            .await
+            .unwrap();
+        assert!(metadata.ended);
+        assert_eq!(metadata.reason.as_deref(), Some("timeout"));
+    }
+}
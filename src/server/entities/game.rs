@@ -11,6 +11,20 @@ pub struct Model {
     pub guest: String,
     pub pending: bool,
     pub ended: bool,
+    pub bot: Option<String>,
+    pub host_black: bool,
+    pub winner: Option<String>,
+    pub black_score: Option<i32>,
+    pub white_score: Option<i32>,
+    pub reason: Option<String>,
+    pub width: i32,
+    pub time_base: Option<i32>,
+    pub time_increment: Option<i32>,
+    pub time_per_move: Option<i32>,
+    pub days_per_move: Option<i32>,
+    pub deadline: Option<DateTimeWithTimeZone>,
+    pub rematch_of: Option<Uuid>,
+    pub visibility: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
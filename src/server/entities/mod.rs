@@ -2,8 +2,10 @@
 
 pub mod prelude;
 
+pub mod chat_message;
 pub mod friend;
 pub mod friend_request;
 pub mod game;
+pub mod game_move;
 pub mod member;
 pub mod session;
@@ -1,7 +1,9 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::chat_message::Entity as ChatMessage;
 pub use super::friend::Entity as Friend;
 pub use super::friend_request::Entity as FriendRequest;
 pub use super::game::Entity as Game;
+pub use super::game_move::Entity as GameMove;
 pub use super::member::Entity as Member;
 pub use super::session::Entity as Session;
@@ -1,39 +1,173 @@
 use crate::{
     companion::Companion,
-    server::{extractors::User, handlers::Response},
-    Game,
+    server::{
+        extractors::User,
+        handlers::{Response, StringError},
+        strings, AppState,
+    },
+    Difficulty, DiscCount, Evaluator, Game, Positional, Settings,
 };
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
 
-/// The default search depth for the companion. Chosen arbitrarily with the goal of providing quality moves in a reasonable amount of time.
-const DEFAULT_DEPTH: usize = 6;
+/// The maximum search depth for the companion. The search deepens iteratively, so in practice
+/// the time budget is what limits it.
+const MAX_DEPTH: usize = 20;
+/// The longest a request may ask the companion to search for, so that no one can tie up the
+/// server with an unbounded search.
+const MAX_TIME: Duration = Duration::from_secs(5);
+/// How long an analysis searches for when the request doesn't say.
+const ANALYSIS_TIME: Duration = Duration::from_secs(1);
 
-/// Provide the best available move for the given game state.
+/// The evaluators a request may choose between.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvaluatorKind {
+    #[default]
+    Positional,
+    DiscCount,
+}
+
+impl EvaluatorKind {
+    fn evaluator(self) -> Box<dyn Evaluator> {
+        match self {
+            Self::Positional => Box::new(Positional::default()),
+            Self::DiscCount => Box::new(DiscCount),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompanionRequest {
+    game: Game,
+    /// A preset for the search parameters. Any parameters given explicitly take precedence.
+    #[serde(default)]
+    difficulty: Difficulty,
+    depth: Option<usize>,
+    /// The time limit for the search, in milliseconds.
+    time: Option<u64>,
+    #[serde(default)]
+    evaluator: EvaluatorKind,
+}
+
+impl CompanionRequest {
+    /// The search parameters for this request, capped to what the server allows.
+    fn settings(&self) -> Settings {
+        let mut settings = self.difficulty.settings();
+        settings.depth = self.depth.unwrap_or(settings.depth).clamp(1, MAX_DEPTH);
+        settings.budget = self
+            .time
+            .map_or(settings.budget, Duration::from_millis)
+            .min(MAX_TIME);
+        settings
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalysisRequest {
+    game: Game,
+    /// The deepest the search may go, in plies.
+    depth: Option<usize>,
+    /// The time limit for the search, in milliseconds.
+    time: Option<u64>,
+    #[serde(default)]
+    evaluator: EvaluatorKind,
+}
+
+/// Provide a move for the given game state at the requested difficulty, and whether it was
+/// taken from the opening book.
 pub async fn companion(
+    State(state): State<Arc<AppState>>,
     _: User, // We don't care who the user is, just that this is an authenticated request
-    body: Json<Game>,
+    Json(body): Json<CompanionRequest>,
+) -> Result<impl IntoResponse, axum::response::Response> {
+    // There's nothing to suggest once the game is over, or when the side to move has to pass.
+    if body.game.moves(body.game.turn()).is_empty() {
+        return Err(StringError(strings::NO_MOVES.to_string(), StatusCode::BAD_REQUEST).into());
+    }
+    let settings = body.settings();
+    let book = Arc::clone(&state.book);
+    // The search is CPU-bound, so keep it off of the async worker threads.
+    let suggestion = tokio::task::spawn_blocking(move || {
+        Companion::from(&body.game)
+            .with_evaluator(body.evaluator.evaluator())
+            .with_settings(&settings, &book)
+            .suggest(settings.depth, settings.budget)
+    })
+    .await
+    .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok(Response::new(suggestion, StatusCode::OK))
+}
+
+/// Score every legal move in the given game state, along with the expected line of play.
+pub async fn analyze(
+    _: User,
+    Json(body): Json<AnalysisRequest>,
 ) -> Result<impl IntoResponse, axum::response::Response> {
-    let mut companion = Companion::from(&body.0);
-    // TODO: Allow the user to specify a custom search depth.
-    let depth = DEFAULT_DEPTH;
-    Ok(Response::new(companion.choice(depth), StatusCode::OK))
+    if body.game.moves(body.game.turn()).is_empty() {
+        return Err(StringError(strings::NO_MOVES.to_string(), StatusCode::BAD_REQUEST).into());
+    }
+    let depth = body.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    let budget = body
+        .time
+        .map_or(ANALYSIS_TIME, Duration::from_millis)
+        .min(MAX_TIME);
+    let analysis = tokio::task::spawn_blocking(move || {
+        Companion::from(&body.game)
+            .with_evaluator(body.evaluator.evaluator())
+            .analyze(depth, Some(budget))
+    })
+    .await
+    .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok(Response::new(analysis, StatusCode::OK))
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
-    use crate::server;
+    use super::{CompanionRequest, MAX_DEPTH, MAX_TIME};
+    use crate::{server, Analysis, Difficulty, Suggestion};
+    use serde_json::json;
+    use std::time::Duration;
     use test_utils::{function, Client};
 
     #[derive(serde::Deserialize)]
     struct Choice {
-        message: (usize, usize),
+        message: Suggestion,
         code: usize,
     }
 
     #[tokio::test]
     async fn new() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(state)).await;
+        let mut game = crate::Game::new();
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let choice: Choice = client
+            .post(&url, "/companion", &json!({ "game": game }))
+            .await;
+        assert_eq!(choice.code, 200);
+        assert!(choice.message.book);
+        assert!(game.moves(game.turn()).contains(&choice.message.square));
+        // The parallel opening is only in the book for one move on each side.
+        for (x, y) in [(5, 4), (5, 3), (4, 2)] {
+            game.place(x, y, game.turn()).unwrap();
+        }
+        let choice: Choice = client
+            .post(&url, "/companion", &json!({ "game": game }))
+            .await;
+        assert!(!choice.message.book);
+        assert!(game.moves(game.turn()).contains(&choice.message.square));
+    }
+
+    #[tokio::test]
+    async fn difficulty() {
         let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
             .await
             .unwrap();
@@ -42,8 +176,88 @@ mod tests {
         let url = test_utils::init(crate::server::app(state)).await;
         let game = crate::Game::new();
         let client = Client::authenticated(&[&function!()], &url, true).await;
-        let choice: Choice = client.post(&url, "/companion", &game).await;
+        // Beginners don't play from the book.
+        let body = json!({ "game": game, "difficulty": "beginner", "evaluator": "disc_count" });
+        let choice: Choice = client.post(&url, "/companion", &body).await;
         assert_eq!(choice.code, 200);
-        assert_eq!(choice.message, (5, 4));
+        assert!(!choice.message.book);
+        assert!(game.moves(game.turn()).contains(&choice.message.square));
+    }
+
+    #[tokio::test]
+    async fn over() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(state)).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        // A full board, and a position where Black is stuck and has to pass.
+        let over = "O".repeat(32) + &"X".repeat(32);
+        let stuck = "OX-".to_string() + &"X".repeat(61);
+        for position in [over, stuck] {
+            let game = crate::Game::from_position(&position, crate::Piece::Black).unwrap();
+            let res: test_utils::Map = client
+                .post(&url, "/companion", &json!({ "game": game }))
+                .await;
+            assert_eq!(res["code"], 400);
+            assert_eq!(res["message"], crate::server::strings::NO_MOVES);
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Analyzed {
+        message: Analysis,
+        code: usize,
+    }
+
+    #[tokio::test]
+    async fn analyze() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(state)).await;
+        let game = crate::Game::new();
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = json!({ "game": game, "depth": 3 });
+        let analysis: Analyzed = client.post(&url, "/analyze", &body).await;
+        assert_eq!(analysis.code, 200);
+        let analysis = analysis.message;
+        assert_eq!(analysis.depth, 3);
+        assert_eq!(analysis.moves.len(), 4);
+        // Every opening move is equivalent.
+        assert!(analysis
+            .moves
+            .iter()
+            .all(|m| m.score == analysis.moves[0].score));
+        assert_eq!(
+            analysis.principal_variation[0],
+            Some(analysis.moves[0].square)
+        );
+    }
+
+    #[test]
+    fn caps() {
+        let game = crate::Game::new();
+        let request: CompanionRequest =
+            serde_json::from_value(json!({ "game": game, "depth": 1000, "time": 1_000_000 }))
+                .unwrap();
+        let settings = request.settings();
+        assert_eq!(settings.depth, MAX_DEPTH);
+        assert_eq!(settings.budget, MAX_TIME);
+        let request: CompanionRequest = serde_json::from_value(
+            json!({ "game": game, "difficulty": "novice", "depth": 0, "time": 50 }),
+        )
+        .unwrap();
+        let settings = request.settings();
+        assert_eq!(settings.depth, 1);
+        assert_eq!(settings.budget, Duration::from_millis(50));
+        assert_eq!(
+            settings.candidates,
+            Difficulty::Novice.settings().candidates
+        );
     }
 }
@@ -1,5 +1,11 @@
 use super::StringError;
-use crate::server::{entities::game, extractors::User, helpers, state::AppState, strings};
+use crate::{
+    server::{
+        bot, clock::TimeControl, create_in_memory_game, entities::game, extractors::User, helpers,
+        state::AppState, strings,
+    },
+    Difficulty, Game,
+};
 use axum::{
     body::Body,
     extract::State,
@@ -13,12 +19,82 @@ use serde_json::json;
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// The opponent for a new game: either another player, by username, or the bot at some
+/// difficulty.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameRequest {
-    guest: String,
+    guest: Option<String>,
+    bot: Option<Difficulty>,
+    #[serde(default)]
+    color: ColorChoice,
+    /// The width of the board, which defaults to a standard 8x8 one.
+    width: Option<u8>,
+    /// How long players have to make their moves. Without one, the game is untimed.
+    time_control: Option<TimeControl>,
+    /// Makes the game a correspondence game, in which players have this many days to make
+    /// each move instead of playing it out in one sitting.
+    days_per_move: Option<u8>,
+    /// Who besides the players can watch the game.
+    #[serde(default)]
+    visibility: Visibility,
+}
+
+/// The values of the time base, increment, time per move and days per move columns.
+type Timing = (Option<i32>, Option<i32>, Option<i32>, Option<i32>);
+
+impl GameRequest {
+    /// How the game is timed. A game can have a clock or days per move, but not both.
+    fn timing(&self) -> Result<Timing, StringError> {
+        let invalid = |message: &str| StringError(message.to_string(), StatusCode::BAD_REQUEST);
+        match (self.time_control, self.days_per_move) {
+            (Some(_), Some(_)) => Err(invalid(strings::CORRESPONDENCE_CLOCK)),
+            (Some(control), None) => {
+                let (base, increment, per_move) = control
+                    .columns()
+                    .ok_or_else(|| invalid(strings::INVALID_TIME_CONTROL))?;
+                Ok((base, increment, per_move, None))
+            }
+            (None, Some(0)) => Err(invalid(strings::INVALID_TIME_CONTROL)),
+            (None, days) => Ok((None, None, None, days.map(i32::from))),
+        }
+    }
+}
+
+/// Who can spectate a game. Its players can always watch it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Private,
+    /// Friends of either player.
+    Friends,
+    /// Anyone who is logged in.
+    Public,
 }
 
-/// Create a new game with the specified host and guest.
+/// The color the host wants to play. The guest plays the other one.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    #[default]
+    Random,
+    Black,
+    White,
+}
+
+impl ColorChoice {
+    /// Whether the host plays Black, picking a side at random if the host didn't choose.
+    fn host_black(self) -> bool {
+        match self {
+            Self::Random => rand::random(),
+            Self::Black => true,
+            Self::White => false,
+        }
+    }
+}
+
+/// Create a new game with the specified host and guest. Games against the bot start right
+/// away, while other games wait for the guest to accept the invite.
 pub async fn create(
     State(state): State<Arc<AppState>>,
     host: User,
@@ -27,33 +103,84 @@ pub async fn create(
     // Fetch the user objects associated with the host and guest usernames to
     // ensure that they exist.
     let host = helpers::get_user(&state, &host.username, true).await?;
-    let guest = helpers::get_user(&state, &body.guest, true).await?;
+    let guest = match (&body.guest, body.bot) {
+        // The bot can't accept invites, so it has to be asked for as a bot.
+        (Some(guest), None) => Some(helpers::get_user(&state, guest, true).await?)
+            .filter(|guest| guest.id != bot::BOT_ID)
+            .ok_or_else(|| {
+                StringError(strings::GAME_OPPONENT.to_string(), StatusCode::BAD_REQUEST)
+                    .into_response()
+            })?,
+        (None, Some(_)) => helpers::get_user(&state, &bot::BOT_ID.to_string(), false).await?,
+        _ => {
+            return Err(
+                StringError(strings::GAME_OPPONENT.to_string(), StatusCode::BAD_REQUEST)
+                    .into_response(),
+            )
+        }
+    };
     // A user can't create a game with themself.
     if host.id == guest.id {
         return Err(
             StringError(strings::GAME_SELF.to_string(), StatusCode::BAD_REQUEST).into_response(),
         );
     }
+    // Only boards that the game supports can be played on.
+    let width = body.width.unwrap_or(8);
+    Game::with_width(usize::from(width))
+        .map_err(|e| StringError(e.to_string(), StatusCode::BAD_REQUEST).into_response())?;
+    let (time_base, time_increment, time_per_move, days_per_move) =
+        body.timing().map_err(IntoResponse::into_response)?;
     // Create a new game record and insert it into the database.
     let id = Uuid::now_v7();
+    let pending = body.bot.is_none();
     let model = game::ActiveModel {
         id: ActiveValue::set(id),
         host: ActiveValue::set(host.id.to_string()),
         guest: ActiveValue::set(guest.id.to_string()),
-        pending: ActiveValue::set(true),
+        pending: ActiveValue::set(pending),
         ended: ActiveValue::set(false),
+        bot: ActiveValue::set(body.bot.and_then(helpers::column)),
+        host_black: ActiveValue::set(body.color.host_black()),
+        winner: ActiveValue::set(None),
+        black_score: ActiveValue::set(None),
+        white_score: ActiveValue::set(None),
+        reason: ActiveValue::set(None),
+        width: ActiveValue::set(i32::from(width)),
+        time_base: ActiveValue::set(time_base),
+        time_increment: ActiveValue::set(time_increment),
+        time_per_move: ActiveValue::set(time_per_move),
+        days_per_move: ActiveValue::set(days_per_move),
+        deadline: ActiveValue::set(None),
+        rematch_of: ActiveValue::set(None),
+        visibility: ActiveValue::set(helpers::column(body.visibility).unwrap_or_default()),
     };
-    model
+    let model = model
         .insert(state.database.as_ref())
         .await
         .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+    let (black, white) = helpers::players(&model);
+    let (black, white) = (black.to_string(), white.to_string());
+    // The bot never needs to accept an invite, so its games can start immediately.
+    if let Some(difficulty) = body.bot {
+        helpers::reset_deadline(&state, &model).await?;
+        create_in_memory_game(&state, &model);
+        bot::spawn(Arc::clone(&state), model, difficulty);
+    }
     Ok(super::Response::new(
         json!({
             "id": id,
             "host": host.id,
             "guest": guest.id,
-            "pending": true,
-            "ended": false
+            "pending": pending,
+            "ended": false,
+            "bot": body.bot,
+            "black": black,
+            "white": white,
+            "width": width,
+            "time_control": body.time_control,
+            "days_per_move": days_per_move,
+            "visibility": body.visibility,
         }),
         StatusCode::CREATED,
     ))
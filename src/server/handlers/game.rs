@@ -1,17 +1,22 @@
 use super::StringError;
-use crate::server::{
-    create_in_memory_game, entities::game::Column, extractors::User, helpers, state::AppState,
-    strings,
+use crate::{
+    server::{
+        chat, clock::TimeControl, create_in_memory_game, entities::game::Column, extractors::User,
+        helpers, packet, state::AppState, strings,
+    },
+    Game,
 };
 use axum::{
     body::Body,
     extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
 use sea_orm::{ActiveModelTrait, IntoActiveModel, ModelTrait, Value};
+use serde::Deserialize;
 use serde_json::json;
-use std::{str::FromStr, sync::Arc};
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Retrieve the details for the specified game.
@@ -25,27 +30,183 @@ pub async fn game(
     let game = helpers::get_game(&state, &id).await?;
     // Convert to strings for more ergonomic comparison.
     let authed = user.id.to_string();
-    let host = game.host.to_string();
-    let guest = game.guest.to_string();
-    // Ensure that the authenticated user is either the host or the guest.
-    (authed == host || authed == guest)
+    let host = game.host.clone();
+    let guest = game.guest.clone();
+    // Ensure that the authenticated user is either the host or the guest, or may watch the
+    // game.
+    if authed == host || authed == guest || helpers::can_spectate(&state, &game, &authed).await? {
         // If so, provide the details for the specified game.
-        .then(|| {
-            Ok(super::Response::new(
-                json!({
-                    "id": game.id,
-                    "pending": game.pending,
-                    "host": game.host,
-                    "guest": game.guest,
-                    "ended": game.ended,
-                }),
-                StatusCode::OK,
-            ))
-        })
+        Ok(super::Response::new(
+            json!({
+                "id": game.id,
+                "pending": game.pending,
+                "host": game.host,
+                "guest": game.guest,
+                "ended": game.ended,
+                "bot": game.bot,
+                "black": helpers::players(&game).0,
+                "white": helpers::players(&game).1,
+                "winner": game.winner,
+                "score": game.black_score.zip(game.white_score),
+                "reason": game.reason,
+                "width": game.width,
+                "time_control": TimeControl::of(&game),
+                "days_per_move": game.days_per_move,
+                "deadline": game.deadline,
+                "visibility": game.visibility,
+                "spectators": packet::spectators(&state, game.id),
+            }),
+            StatusCode::OK,
+        ))
+    } else {
         // Otherwise, pretend the game does not exist.
-        .unwrap_or_else(|| {
-            Err(StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND).into_response())
+        Err(StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND).into_response())
+    }
+}
+
+/// Retrieve every move and pass made in the specified game, with the discs each one flipped
+/// and the score after it.
+pub async fn moves(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    user: User,
+) -> Result<impl IntoResponse, Response<Body>> {
+    let (id, current) = played(&state, &id, &user).await?;
+    Ok(super::Response::new(
+        json!({
+            "id": id,
+            "moves": current.steps(),
+        }),
+        StatusCode::OK,
+    ))
+}
+
+/// Retrieve every message sent in the specified game's chat, oldest first. Spectators can
+/// read it too, as long as they may watch the game.
+pub async fn chat(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    user: User,
+) -> Result<impl IntoResponse, Response<Body>> {
+    let user = helpers::get_user(&state, &user.username, true).await?;
+    let game = helpers::get_game(&state, &id).await?;
+    let authed = user.id.to_string();
+    if helpers::piece(&game, &authed).is_none()
+        && !helpers::can_spectate(&state, &game, &authed).await?
+    {
+        // Pretend the game does not exist.
+        return Err(
+            StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND).into_response(),
+        );
+    }
+    // Messages are only ever sent by the two players, so their usernames are looked up once.
+    let mut usernames = std::collections::HashMap::new();
+    for player in [&game.host, &game.guest] {
+        let username = helpers::get_user(&state, player, false).await?.username;
+        usernames.insert(player.clone(), username);
+    }
+    let messages = chat::history(&state, game.id).await?;
+    let messages: Vec<_> = messages
+        .into_iter()
+        .map(|message| {
+            json!({
+                "author": usernames.get(&message.author),
+                "piece": message.color,
+                "message": message.message,
+                "sent_at": message.sent_at,
+            })
         })
+        .collect();
+    Ok(super::Response::new(
+        json!({
+            "id": game.id,
+            "messages": messages,
+        }),
+        StatusCode::OK,
+    ))
+}
+
+/// Retrieve the moves made in the specified game as a transcript, such as `f5d6c3d3c4`.
+pub async fn transcript(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    user: User,
+) -> Result<impl IntoResponse, Response<Body>> {
+    let (id, current) = played(&state, &id, &user).await?;
+    Ok(super::Response::new(
+        json!({
+            "id": id,
+            "transcript": current.transcript(),
+        }),
+        StatusCode::OK,
+    ))
+}
+
+/// A move in a correspondence game, made without joining the game's room.
+#[derive(Debug, Deserialize)]
+pub struct MoveRequest {
+    x: usize,
+    y: usize,
+}
+
+/// Place the authenticated user's piece at the requested square, with the same checks as a
+/// move made over the websocket. The game is sent back as it stands after the move.
+pub async fn play(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    user: User,
+    Json(body): Json<MoveRequest>,
+) -> Result<impl IntoResponse, Response<Body>> {
+    let user = helpers::get_user(&state, &user.username, true).await?;
+    let game = helpers::get_game(&state, &id).await?;
+    // Only the players can move, and they can only place their own color.
+    let piece = helpers::piece(&game, &user.id.to_string()).ok_or_else(|| {
+        StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND).into_response()
+    })?;
+    if game.ended {
+        return Err(
+            StringError(strings::GAME_OVER.into(), StatusCode::BAD_REQUEST).into_response(),
+        );
+    }
+    packet::play(&state, &game, body.x, body.y, piece)
+        .await
+        .map_err(|e| StringError::from(e).into_response())?;
+    let current = {
+        let games = state.games.lock().expect("mutex was poisoned");
+        games.get(&game.id).cloned()
+    };
+    Ok(super::Response::new(
+        json!({
+            "id": game.id,
+            "game": current,
+        }),
+        StatusCode::OK,
+    ))
+}
+
+/// The specified game as it has been played so far, provided the authenticated user is one
+/// of its players.
+async fn played(state: &AppState, id: &str, user: &User) -> Result<(Uuid, Game), Response> {
+    // Fetch the user and game from the database.
+    let user = helpers::get_user(state, &user.username, true).await?;
+    let game = helpers::get_game(state, id).await?;
+    // Ensure that the authenticated user is either the host or the guest.
+    if helpers::piece(&game, &user.id.to_string()).is_none() {
+        // Otherwise, pretend the game does not exist.
+        return Err(
+            StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND).into_response(),
+        );
+    }
+    // Games that aren't being played right now are rebuilt from their recorded moves.
+    let current = {
+        let games = state.games.lock().expect("mutex was poisoned");
+        games.get(&game.id).cloned()
+    };
+    let current = match current {
+        Some(current) => current,
+        None => helpers::replay(state, &game).await?,
+    };
+    Ok((game.id, current))
 }
 
 pub async fn cancel(
@@ -58,7 +219,7 @@ pub async fn cancel(
     let game = helpers::get_game(&state, &id).await?;
     // Convert to strings for more ergonomic comparison.
     let authed = user.id.to_string();
-    let host = game.host.to_string();
+    let host = game.host.clone();
     // Ensure that the authenticated user is the host.
     if authed == host {
         // If so, delete the game record from the database.
@@ -83,19 +244,19 @@ pub async fn accept(
     let game = helpers::get_game(&state, &id).await?;
     // Convert to strings for more ergonomic comparison.
     let authed = user.id.to_string();
-    let guest = game.guest.to_string();
+    let guest = game.guest.clone();
     // Ensure that the authenticated user is the guest.
     if authed == guest {
         // If so, update the game record to indicate that the game is no longer pending.
         let game = helpers::get_game(&state, &id).await?;
-        let mut active = game.into_active_model();
+        let mut active = game.clone().into_active_model();
         active.set(Column::Pending, Value::Bool(Some(false)));
         active
             .save(state.database.as_ref())
             .await
             .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
-        let gid = Uuid::from_str(&id).unwrap();
-        create_in_memory_game(&state, gid);
+        helpers::reset_deadline(&state, &game).await?;
+        create_in_memory_game(&state, &game);
         Ok(super::Response::new(json!({}), StatusCode::OK))
     } else {
         // Otherwise, pretend the game does not exist.
@@ -113,7 +274,7 @@ pub async fn decline(
     let game = helpers::get_game(&state, &id).await?;
     // Convert to strings for more ergonomic comparison.
     let authed = user.id.to_string();
-    let guest = game.guest.to_string();
+    let guest = game.guest.clone();
     // Ensure that the authenticated user is the guest.
     if authed == guest {
         // If so, delete the game record from the database.
@@ -127,3 +288,168 @@ pub async fn decline(
         Err(StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND).into_response())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{
+        self,
+        entities::{game::Column, prelude::Game},
+        helpers, packet,
+    };
+    use crate::Piece;
+    use sea_orm::{sea_query::Expr, ColumnTrait, EntityTrait, QueryFilter};
+    use std::sync::Arc;
+    use test_utils::{function, Client, Map};
+
+    #[tokio::test]
+    async fn moves() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap();
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        packet::play(&state, &metadata, 5, 4, Piece::Black)
+            .await
+            .unwrap();
+        let res: Map = client.get(&url, &format!("/game/{id}/moves")).await;
+        assert_eq!(res["code"], 200);
+        let first = &res["message"]["moves"][0];
+        assert_eq!(first["square"], serde_json::json!([5, 4]));
+        assert_eq!(first["piece"], "Black");
+        assert_eq!(first["flips"], serde_json::json!([[4, 4]]));
+        assert_eq!(first["score"], serde_json::json!([4, 1]));
+        let res: Map = client.get(&url, &format!("/game/{id}/transcript")).await;
+        assert!(res["message"]["transcript"]
+            .as_str()
+            .unwrap()
+            .starts_with("f5"));
+        // Only the players can look through the game.
+        let other = Client::authenticated(&[&format!("{}2", function!())], &url, true).await;
+        let res: Map = other.get(&url, &format!("/game/{id}/moves")).await;
+        assert_eq!(res["code"], 404);
+    }
+
+    #[tokio::test]
+    async fn widths() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "black", "width": 7 });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 400);
+        let body = serde_json::json!({ "bot": "beginner", "color": "black", "width": 6 });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 201);
+        assert_eq!(res["message"]["width"], 6);
+        let id = res["message"]["id"].as_str().unwrap();
+        let res: Map = client.get(&url, &format!("/game/{id}")).await;
+        assert_eq!(res["message"]["width"], 6);
+        // Moves are checked against the smaller board, and replays start from it.
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        assert!(packet::play(&state, &metadata, 7, 7, Piece::Black)
+            .await
+            .is_err());
+        packet::play(&state, &metadata, 3, 4, Piece::Black)
+            .await
+            .unwrap();
+        let replayed = helpers::replay(&state, &metadata).await.unwrap();
+        assert_eq!(replayed.width(), 6);
+        assert_eq!(replayed.history()[0], Some((3, 4)));
+    }
+
+    #[tokio::test]
+    async fn correspondence() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        // Days per move take the place of a clock, rather than adding to one.
+        let body = serde_json::json!({
+            "guest": guest,
+            "days_per_move": 3,
+            "time_control": { "type": "per_move", "seconds": 30 },
+        });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 400);
+        let body = serde_json::json!({ "guest": guest, "color": "black", "days_per_move": 3 });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 201);
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let _: Map = other
+            .post(
+                &url,
+                &format!("/@me/games/{id}/accept"),
+                &serde_json::json!({}),
+            )
+            .await;
+        // The deadline starts once the invite is accepted.
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        let deadline = metadata.deadline.unwrap();
+        // The players keep their games from earlier runs, so only look at this one.
+        let your_turn = |res: Map| {
+            let games = res["message"].as_array().unwrap().clone();
+            let game = games.into_iter().find(|game| game["id"] == id.as_str());
+            game.unwrap()["your_turn"].clone()
+        };
+        let res: Map = client.get(&url, "/@me/games").await;
+        assert_eq!(your_turn(res), true);
+        // Moves can be made without joining the game's room, with the usual checks.
+        let endpoint = format!("/game/{id}/move");
+        let res: Map = client
+            .post(&url, &endpoint, &serde_json::json!({ "x": 0, "y": 0 }))
+            .await;
+        assert_eq!(res["code"], 400);
+        let res: Map = client
+            .post(&url, &endpoint, &serde_json::json!({ "x": 5, "y": 4 }))
+            .await;
+        assert_eq!(res["code"], 200);
+        assert_eq!(res["message"]["game"]["turn"], "White");
+        let res: Map = client
+            .post(&url, &endpoint, &serde_json::json!({ "x": 5, "y": 5 }))
+            .await;
+        assert_eq!(res["code"], 400);
+        let res: Map = client.get(&url, "/@me/games").await;
+        assert_eq!(your_turn(res), false);
+        let res: Map = other.get(&url, "/@me/games").await;
+        assert_eq!(your_turn(res), true);
+        // Each move gives the other player a fresh deadline.
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        assert!(metadata.deadline.unwrap() > deadline);
+        // White misses their deadline, so Black wins on time.
+        Game::update_many()
+            .col_expr(Column::Deadline, Expr::cust("now() - interval '1 minute'"))
+            .filter(Column::Id.eq(metadata.id))
+            .exec(state.database.as_ref())
+            .await
+            .unwrap();
+        assert!(
+            server::correspondence::forfeit_overdue(&state)
+                .await
+                .unwrap()
+                >= 1
+        );
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        assert!(metadata.ended);
+        assert_eq!(metadata.reason.as_deref(), Some("timeout"));
+        assert_eq!(metadata.winner.as_deref(), Some(metadata.host.as_str()));
+        let res: Map = other
+            .post(&url, &endpoint, &serde_json::json!({ "x": 5, "y": 5 }))
+            .await;
+        assert_eq!(res["code"], 400);
+    }
+}
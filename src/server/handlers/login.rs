@@ -1,7 +1,8 @@
-use crate::server::{helpers, state::AppState, strings};
+use crate::server::{bot, handlers::StringError, helpers, state::AppState, strings};
 use axum::{
     body::Body,
     extract::State,
+    http::StatusCode,
     response::{IntoResponse, Redirect, Response},
     Json,
 };
@@ -25,6 +26,12 @@ pub async fn login(
 ) -> Result<impl IntoResponse, Response<Body>> {
     let Credentials { username, password } = credentials;
     let user = helpers::get_user(&state, &username, true).await?;
+    // The bot has no password to check against, so nobody can log in as it.
+    if user.id == bot::BOT_ID {
+        return Err(
+            StringError(strings::INVALID_PASSWORD.to_string(), StatusCode::FORBIDDEN).into(),
+        );
+    }
     helpers::ensure_valid_password(&user.password, &password)?;
     // Generate a random key to use as the session token.
     let key = {
@@ -46,8 +53,8 @@ pub async fn login(
 mod tests {
     use std::sync::Arc;
 
-    use crate::server;
-    use test_utils::{function, Client};
+    use crate::server::{self, bot::BOT_ID, helpers};
+    use test_utils::{function, Client, Map};
 
     #[tokio::test]
     async fn login() {
@@ -61,4 +68,20 @@ mod tests {
         let res: serde_json::Value = client.get(&url, "/@me").await;
         assert_eq!(&res["code"], &200);
     }
+
+    #[tokio::test]
+    async fn bot() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let bot = helpers::get_user(&state, &BOT_ID.to_string(), false)
+            .await
+            .unwrap();
+        let body = serde_json::json!({ "username": bot.username, "password": "" });
+        let res: Map = Client::new().post(&url, "/login", &body).await;
+        assert_eq!(res["code"], 403);
+    }
 }
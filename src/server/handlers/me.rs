@@ -69,7 +69,7 @@ pub async fn update(
             let mut active = stored.into_active_model();
             active.set(
                 Column::Username,
-                Value::String(Some(Box::new(username.to_string()))),
+                Value::String(Some(Box::new(username.clone()))),
             );
             active
                 .save(state.database.as_ref())
@@ -109,7 +109,7 @@ pub async fn update(
             let mut active = stored.into_active_model();
             active.set(
                 Column::Password,
-                Value::String(Some(Box::new(hashed.to_string()))),
+                Value::String(Some(Box::new(hashed.clone()))),
             );
             active
                 .save(state.database.as_ref())
@@ -274,11 +274,24 @@ async fn create_games_resp(
         };
         let host = helpers::get_user(&state, g.host.as_str(), false).await?;
         let opponent = helpers::get_user(&state, id, false).await?;
+        let color = helpers::piece(g, &user.id.to_string());
+        // Only games being played have a turn, so it's nobody's turn in any other game.
+        let turn = {
+            let games = state.games.lock().expect("mutex was poisoned");
+            games
+                .get(&g.id)
+                .filter(|game| !g.ended && !game.over())
+                .map(crate::Game::turn)
+        };
         resp.push(json!({
             "id": g.id,
             "host": host.username,
             "opponent": opponent.username,
             "ended": g.ended,
+            "bot": g.bot,
+            "color": color,
+            "your_turn": turn.is_some() && turn == color,
+            "deadline": g.deadline,
         }));
     }
     Ok(resp)
@@ -11,9 +11,12 @@ mod logout;
 mod me;
 mod register;
 
-pub use companion::companion;
-pub use create::create;
-pub use game::{accept as accept_game, cancel as cancel_invite, decline as decline_game, game};
+pub use companion::{analyze, companion};
+pub use create::{create, Visibility};
+pub use game::{
+    accept as accept_game, cancel as cancel_invite, chat, decline as decline_game, game, moves,
+    play, transcript,
+};
 pub use live::callback;
 pub use login::login;
 pub use logout::logout;
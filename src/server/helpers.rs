@@ -1,14 +1,21 @@
-use crate::server::{
-    entities::{game, member, prelude::*, session},
-    handlers::StringError,
-    strings, AppState, PasswordHash, StatusCode,
+use crate::{
+    server::{
+        entities::{friend, game, game_move, member, prelude::*, session},
+        handlers::{StringError, Visibility},
+        strings, AppState, PasswordHash, StatusCode,
+    },
+    Piece,
 };
 use argon2::{Argon2, PasswordVerifier};
-use sea_orm::{sea_query::OnConflict, ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveValue, ColumnTrait, EntityTrait, QueryFilter, QueryOrder,
+};
+use serde::{de::DeserializeOwned, Serialize};
 use uuid::Uuid;
 
 /// Hashes a password string.
-fn hash(s: &str) -> Result<PasswordHash, StringError> {
+fn hash(s: &str) -> Result<PasswordHash<'_>, StringError> {
     PasswordHash::new(s).map_err(|_| {
         StringError(
             strings::INVALID_PASSWORD_FORMAT.to_string(),
@@ -131,3 +138,166 @@ pub fn ensure_valid_password(actual: &str, provided: &str) -> Result<(), StringE
         .verify_password(provided.as_bytes(), &hashed)
         .map_err(|_| StringError(strings::INVALID_PASSWORD.to_string(), StatusCode::FORBIDDEN))
 }
+
+/// The color played by the member with ID `member` in `game`, or `None` if they aren't
+/// playing in it.
+pub fn piece(game: &game::Model, member: &str) -> Option<Piece> {
+    let host = if game.host_black {
+        Piece::Black
+    } else {
+        Piece::White
+    };
+    match member {
+        _ if member == game.host => Some(host),
+        _ if member == game.guest => Some(!host),
+        _ => None,
+    }
+}
+
+/// Whether the members with IDs `a` and `b` are friends.
+pub async fn are_friends(state: &AppState, a: &str, b: &str) -> Result<bool, StringError> {
+    let (Ok(a), Ok(b)) = (Uuid::parse_str(a), Uuid::parse_str(b)) else {
+        return Ok(false);
+    };
+    Friend::find()
+        .filter(
+            friend::Column::A
+                .eq(a)
+                .and(friend::Column::B.eq(b))
+                .or(friend::Column::A.eq(b).and(friend::Column::B.eq(a))),
+        )
+        .one(state.database.as_ref())
+        .await
+        .map(|friend| friend.is_some())
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Whether the member with ID `member` may watch `game` without playing in it, going by the
+/// game's visibility.
+pub async fn can_spectate(
+    state: &AppState,
+    game: &game::Model,
+    member: &str,
+) -> Result<bool, StringError> {
+    match parse_column(&game.visibility).unwrap_or_default() {
+        Visibility::Private => Ok(false),
+        Visibility::Friends => Ok(are_friends(state, member, &game.host).await?
+            || are_friends(state, member, &game.guest).await?),
+        Visibility::Public => Ok(true),
+    }
+}
+
+/// The IDs of the members playing black and white in `game`, in that order.
+pub fn players(game: &game::Model) -> (&str, &str) {
+    if game.host_black {
+        (&game.host, &game.guest)
+    } else {
+        (&game.guest, &game.host)
+    }
+}
+
+/// How `value` is stored in a text column, using its serialized name. Only meant for enums
+/// with unit variants, which serialize to strings.
+pub fn column<T: Serialize>(value: T) -> Option<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => Some(name),
+        _ => None,
+    }
+}
+
+/// Reads a value stored by [`column`].
+pub fn parse_column<T: DeserializeOwned>(column: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(column.to_string())).ok()
+}
+
+/// Records the move at (x, y) as ply `ply` of the game with ID `id`.
+pub async fn record_move(
+    state: &AppState,
+    id: Uuid,
+    ply: usize,
+    (x, y): (usize, usize),
+    piece: Piece,
+    flips: usize,
+) -> Result<(), StringError> {
+    let int = |n: usize| {
+        i32::try_from(n).map_err(|e| StringError(e.to_string(), StatusCode::BAD_REQUEST))
+    };
+    GameMove::insert(game_move::ActiveModel {
+        game_id: ActiveValue::set(id),
+        ply: ActiveValue::set(int(ply)?),
+        x: ActiveValue::set(int(x)?),
+        y: ActiveValue::set(int(y)?),
+        color: ActiveValue::set(column(piece).unwrap_or_default()),
+        flips: ActiveValue::set(int(flips)?),
+        played_at: ActiveValue::NotSet,
+    })
+    .exec(state.database.as_ref())
+    .await
+    .map(|_| ())
+    .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Deletes the recorded moves of the game with ID `id` from ply `from` onward, once they have
+/// been taken back.
+pub async fn delete_moves(state: &AppState, id: Uuid, from: usize) -> Result<(), StringError> {
+    let from =
+        i32::try_from(from).map_err(|e| StringError(e.to_string(), StatusCode::BAD_REQUEST))?;
+    GameMove::delete_many()
+        .filter(game_move::Column::GameId.eq(id))
+        .filter(game_move::Column::Ply.gte(from))
+        .exec(state.database.as_ref())
+        .await
+        .map(|_| ())
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Gives the player to move in the correspondence game described by `metadata` its full
+/// allowance of days, counting from now. Games without a per-move deadline are left alone.
+pub async fn reset_deadline(state: &AppState, metadata: &game::Model) -> Result<(), StringError> {
+    let Some(days) = metadata.days_per_move else {
+        return Ok(());
+    };
+    Game::update_many()
+        .col_expr(
+            game::Column::Deadline,
+            Expr::cust(format!("now() + interval '{days} days'")),
+        )
+        .filter(game::Column::Id.eq(metadata.id))
+        .exec(state.database.as_ref())
+        .await
+        .map(|_| ())
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// The moves played in the game with ID `id`, in the order they were played.
+pub async fn get_moves(state: &AppState, id: Uuid) -> Result<Vec<game_move::Model>, StringError> {
+    GameMove::find()
+        .filter(game_move::Column::GameId.eq(id))
+        .order_by_asc(game_move::Column::Ply)
+        .all(state.database.as_ref())
+        .await
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// A new game on the board that `game` was created with.
+#[must_use]
+pub fn start(game: &game::Model) -> crate::Game {
+    usize::try_from(game.width)
+        .ok()
+        .and_then(|width| crate::Game::with_width(width).ok())
+        .unwrap_or_default()
+}
+
+/// Rebuilds `metadata`'s game by replaying its recorded moves from the start. Passes aren't
+/// recorded, but replaying the moves makes them again.
+pub async fn replay(state: &AppState, metadata: &game::Model) -> Result<crate::Game, StringError> {
+    let mut game = start(metadata);
+    for row in get_moves(state, metadata.id).await? {
+        let invalid = || StringError(strings::INVALID_MOVE_HISTORY.into(), StatusCode::CONFLICT);
+        let piece = parse_column(&row.color).ok_or_else(invalid)?;
+        let x = usize::try_from(row.x).map_err(|_| invalid())?;
+        let y = usize::try_from(row.y).map_err(|_| invalid())?;
+        game.place(x, y, piece).map_err(|_| invalid())?;
+    }
+    Ok(game)
+}
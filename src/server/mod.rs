@@ -17,11 +17,16 @@ use uuid::Uuid;
 
 pub use state::AppState;
 
+mod bot;
+mod chat;
+mod clock;
+mod correspondence;
 mod entities;
 mod extractors;
 mod handlers;
 mod helpers;
 mod packet;
+mod rematch;
 mod state;
 mod strings;
 
@@ -56,6 +61,22 @@ pub fn app(state: Arc<AppState>) -> Router {
             "/game/:id",
             get(handlers::game).with_state(Arc::clone(&state)),
         )
+        .route(
+            "/game/:id/chat",
+            get(handlers::chat).with_state(Arc::clone(&state)),
+        )
+        .route(
+            "/game/:id/move",
+            post(handlers::play).with_state(Arc::clone(&state)),
+        )
+        .route(
+            "/game/:id/moves",
+            get(handlers::moves).with_state(Arc::clone(&state)),
+        )
+        .route(
+            "/game/:id/transcript",
+            get(handlers::transcript).with_state(Arc::clone(&state)),
+        )
         .route(
             "/users/:id/friend",
             post(handlers::friend_request::send).with_state(Arc::clone(&state)),
@@ -109,7 +130,11 @@ pub fn app(state: Arc<AppState>) -> Router {
             "/@me/friends/:id/:outcome",
             post(handlers::friend_request::reply).with_state(Arc::clone(&state)),
         )
-        .route("/companion", post(handlers::companion).with_state(state))
+        .route(
+            "/companion",
+            post(handlers::companion).with_state(Arc::clone(&state)),
+        )
+        .route("/analyze", post(handlers::analyze).with_state(state))
         .fallback(handlers::fallback)
         // TODO: Use a proper CORS policy.
         .layer(CorsLayer::very_permissive())
@@ -122,20 +147,40 @@ async fn handler(
     ws.on_upgrade(|socket| handlers::callback(socket, state))
 }
 
-/// Create a new game with the specified host and guest.
+/// Create a new game with the specified host and guest, on the board and with the time control
+/// it was created with.
 /// # Panics
 /// Panics if the mutex is poisoned.
-pub fn create_in_memory_game(state: &Arc<AppState>, gid: Uuid) {
+pub fn create_in_memory_game(state: &Arc<AppState>, metadata: &entities::game::Model) {
+    let game = cached_game(state, metadata.id).unwrap_or_else(|| helpers::start(metadata));
+    let turn = game.turn();
+    insert_in_memory_game(state, metadata.id, game);
+    clock::start(state, metadata, turn);
+}
+
+/// The game with ID `gid` as it was last cached in Redis, if it's there. A copy that can't be
+/// read, such as one cached in an older format, is treated as missing so that the game is
+/// rebuilt from its recorded moves instead.
+fn cached_game(state: &AppState, gid: Uuid) -> Option<Game> {
+    let cached = state
+        .redis
+        .get_connection()
+        .and_then(|mut conn| conn.get::<String, String>(format!("game:{gid}")))
+        .ok()?;
+    let game: Game = match serde_json::from_str(&cached) {
+        Ok(game) => game,
+        Err(e) => {
+            log::warn!("ignoring unreadable cached copy of {gid:?}: {e}");
+            return None;
+        }
+    };
+    log::info!("Restoring {gid:?} from cache: raw {cached}");
+    Some(game)
+}
+
+fn insert_in_memory_game(state: &AppState, gid: Uuid, game: Game) {
     // Create a new game object and broadcast channel for notifications to websocket
     // subscribers.
-    let mut conn = state.redis.get_connection().unwrap();
-    let game = if let Ok(cached) = conn.get::<String, String>(format!("game:{gid}")) {
-        let game: Game = serde_json::from_str(&cached).unwrap();
-        log::info!("Restoring {gid:?} from cache: raw {cached}");
-        game
-    } else {
-        Game::new()
-    };
     let (tx, _) = broadcast::channel(16);
     // Insert the game object and broadcast channel into the global state.
     let mut games = state.games.lock().expect("mutex was poisoned");
@@ -144,21 +189,47 @@ pub fn create_in_memory_game(state: &Arc<AppState>, gid: Uuid) {
     rooms.insert(gid, tx);
 }
 
-/// Restore any active games to the cache.
+/// Restore any active games to the cache. A game that can't be rebuilt is logged and left
+/// out, so that it doesn't keep the others from coming back. Ended games stay in the database,
+/// and are rebuilt from their moves whenever they're asked for.
 /// # Errors
 /// If an error occurs while querying the database, it will be returned as a string.
 pub async fn restore_active_games(state: &Arc<AppState>) -> Result<(), String> {
     let games = entities::game::Entity::find()
         .filter(Column::Pending.eq(false))
+        .filter(Column::Ended.eq(false))
         .all(state.database.as_ref())
         .await
         .map_err(|e| e.to_string())?;
-    for game in &games {
-        create_in_memory_game(state, game.id);
+    for game in games {
+        // Without a cached copy, the game can still be rebuilt from its recorded moves.
+        let restored = match cached_game(state, game.id) {
+            Some(restored) => restored,
+            None => match helpers::replay(state, &game).await {
+                Ok(restored) => restored,
+                Err(StringError(message, _)) => {
+                    log::error!("failed to restore {}: {message}", game.id);
+                    continue;
+                }
+            },
+        };
+        let turn = restored.turn();
+        insert_in_memory_game(state, game.id, restored);
+        clock::start(state, &game, turn);
+        // Pick bot games back up where they were left off.
+        if let Some(difficulty) = bot::difficulty(&game) {
+            bot::spawn(Arc::clone(state), game, difficulty);
+        }
     }
     Ok(())
 }
 
+/// Starts checking for correspondence games whose deadline has passed, forfeiting them on
+/// behalf of the player who didn't move in time.
+pub fn schedule_deadlines(state: &Arc<AppState>) {
+    correspondence::spawn(Arc::clone(state));
+}
+
 /// Validates a username according to the following rules:
 /// - At least three characters long
 /// # Errors
@@ -1,12 +1,13 @@
 use crate::{
     server::{
+        bot, chat, clock,
         entities::{game, prelude::Game as GameModel},
         handlers::StringError,
-        helpers,
+        helpers, rematch,
         state::AppState,
         strings,
     },
-    Game, Piece,
+    Game, GameResult, Piece, Reason,
 };
 use axum::{extract::ws::Message, http::StatusCode};
 use futures::Future;
@@ -14,8 +15,12 @@ use redis::Commands;
 use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use std::str::FromStr;
-use tokio::sync::mpsc;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{broadcast, mpsc};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,7 +38,10 @@ enum Data {
         id: String,
         x: usize,
         y: usize,
-        piece: Piece,
+        /// The color the client thinks it's playing. The server works this out from the
+        /// session instead, so this is only checked if it's given.
+        #[serde(default)]
+        piece: Option<Piece>,
     },
     Create {
         guest: String,
@@ -47,6 +55,41 @@ enum Data {
     End {
         id: String,
     },
+    Takeback {
+        id: String,
+    },
+    AnswerTakeback {
+        id: String,
+        accept: bool,
+    },
+    Resign {
+        id: String,
+    },
+    OfferDraw {
+        id: String,
+    },
+    AcceptDraw {
+        id: String,
+    },
+    DeclineDraw {
+        id: String,
+    },
+    Rematch {
+        id: String,
+    },
+    AcceptRematch {
+        id: String,
+    },
+    Spectate {
+        id: String,
+    },
+    Chat {
+        id: String,
+        message: String,
+    },
+    Mute {
+        id: String,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
@@ -59,6 +102,17 @@ enum Opcode {
     Reserved,
     Identify,
     Preview,
+    Takeback,
+    AnswerTakeback,
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    Rematch,
+    AcceptRematch,
+    Spectate,
+    Chat,
+    Mute,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -80,7 +134,11 @@ impl TryFrom<&Message> for Packet {
 }
 
 impl Packet {
-    pub async fn process(&self, state: &AppState, sender: Option<mpsc::Sender<Event>>) -> Event {
+    pub async fn process(
+        &self,
+        state: &Arc<AppState>,
+        sender: Option<mpsc::Sender<Event>>,
+    ) -> Event {
         match self.op {
             Opcode::Identify => self.identify(state).await,
             Opcode::Place => self.authenticated(state, |p| p.place(state)).await,
@@ -90,6 +148,28 @@ impl Packet {
                     .await
             }
             Opcode::Leave => self.authenticated(state, |p| p.leave(state)).await,
+            Opcode::Takeback => {
+                self.authenticated(state, |p| p.request_takeback(state))
+                    .await
+            }
+            Opcode::AnswerTakeback => {
+                self.authenticated(state, |p| p.answer_takeback(state))
+                    .await
+            }
+            Opcode::Resign => self.authenticated(state, |p| p.resign(state)).await,
+            Opcode::OfferDraw => self.authenticated(state, |p| p.offer_draw(state)).await,
+            Opcode::AcceptDraw => self.authenticated(state, |p| p.accept_draw(state)).await,
+            Opcode::DeclineDraw => self.authenticated(state, |p| p.decline_draw(state)).await,
+            Opcode::Spectate => {
+                self.authenticated(state, |p| {
+                    p.spectate(state, sender.expect("missing sender"))
+                })
+                .await
+            }
+            Opcode::Rematch => self.authenticated(state, |p| p.rematch(state)).await,
+            Opcode::AcceptRematch => self.authenticated(state, |p| p.accept_rematch(state)).await,
+            Opcode::Chat => self.authenticated(state, |p| p.chat(state)).await,
+            Opcode::Mute => self.authenticated(state, |p| p.mute(state)).await,
             Opcode::Reserved => Ok(Event::error(
                 strings::RESERVED_OPCODE,
                 StatusCode::BAD_REQUEST,
@@ -109,34 +189,24 @@ impl Packet {
             panic!("expected serde to reject invalid packet data")
         };
         // Verify that the authenticated user is either the host or guest of the game.
-        self.ensure_participant(state, id).await?;
-        let uuid = Uuid::from_str(id)
-            .map_err(|_| Event::error(strings::INVALID_GAME_ID_FORMAT, StatusCode::BAD_REQUEST))?;
-        // Subscribe to the broadcast channel for the specified room.
-        let mut rooms = state.rooms.lock().expect("mutex was poisoned");
-        let mut rx = rooms
-            .get_mut(&uuid)
-            .ok_or(Event::error(
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        let uuid = metadata.id;
+        // Send the current state of the room.
+        let game = {
+            let games = state.games.lock().expect("mutex was poisoned");
+            games.get(&uuid).cloned().ok_or(Event::error(
                 strings::INVALID_GAME_ID,
                 StatusCode::NOT_FOUND,
             ))?
-            .subscribe();
-        // Send the current state of the room.
-        let games = state.games.lock().expect("mutex was poisoned");
-        let game = games.get(&uuid).ok_or(Event::error(
-            strings::INVALID_GAME_ID,
-            StatusCode::NOT_FOUND,
-        ))?;
-        // Spawn a task to listen for room updates to broadcast.
-        tokio::spawn(async move {
-            while let Ok(update) = rx.recv().await {
-                let _ = sender.send(update).await;
-            }
-        });
-        Ok(Event::new(
-            EventKind::GameUpdate,
-            EventData::GameUpdate { game: game.clone() },
-        ))
+        };
+        // Subscribe to the broadcast channel for the specified room.
+        if !subscribe(state, uuid, piece, sender) {
+            return Err(Event::error(
+                strings::INVALID_GAME_ID,
+                StatusCode::NOT_FOUND,
+            ));
+        }
+        Ok(update(state, uuid, &game))
     }
 
     async fn leave(&self, state: &AppState) -> Result<Event, Event> {
@@ -170,6 +240,21 @@ impl Packet {
             StatusCode::NOT_FOUND,
         ))?;
         rooms.remove(&uuid).unwrap();
+        for pending in [&state.takebacks, &state.draws] {
+            pending.lock().expect("mutex was poisoned").remove(&uuid);
+        }
+        state
+            .clocks
+            .lock()
+            .expect("mutex was poisoned")
+            .remove(&uuid);
+        // The game is gone, so there won't be a rematch to move its sockets into.
+        state
+            .sockets
+            .lock()
+            .expect("mutex was poisoned")
+            .remove(&uuid);
+        chat::forget(state, uuid);
         Ok(Event::new(EventKind::Ack, EventData::Ack))
     }
 
@@ -178,65 +263,8 @@ impl Packet {
             panic!("expected serde to reject invalid packet data")
         };
         // Verify that the authenticated user is either the host or guest of the game.
-        self.ensure_participant(state, id).await?;
-        let metadata = self.game(state, id).await?;
-        let uuid = Uuid::from_str(id)
-            .map_err(|_| Event::error(strings::INVALID_GAME_ID_FORMAT, StatusCode::BAD_REQUEST))?;
-        let tx = {
-            let mut rooms = state.rooms.lock().expect("mutex was poisoned");
-            rooms
-                .get_mut(&uuid)
-                .ok_or(Event::error(
-                    strings::INVALID_GAME_ID,
-                    StatusCode::NOT_FOUND,
-                ))?
-                .clone()
-        };
-        let (res, mut game) = {
-            let mut games = state.games.lock().expect("mutex was poisoned");
-            let game = games.get_mut(&uuid).ok_or(Event::error(
-                strings::INVALID_GAME_ID,
-                StatusCode::NOT_FOUND,
-            ))?;
-            let res = game.place(*x, *y, *piece).map_or_else(
-                |e| Err(Event::error(&e.to_string(), StatusCode::BAD_REQUEST)),
-                |()| Ok(Event::new(EventKind::Ack, EventData::Ack)),
-            )?;
-            let _ = tx.send(Event::new(
-                EventKind::GameUpdate,
-                EventData::GameUpdate { game: game.clone() },
-            ));
-            if let Ok(mut conn) = state.redis.get_connection() {
-                let _ = conn.set::<String, String, String>(
-                    format!("game:{}", id.clone()),
-                    serde_json::to_string(game).unwrap(),
-                );
-            }
-            (res, game.clone())
-        };
-        if game.over() {
-            let (black, white) = game.score();
-            let winner = if black > white {
-                metadata.host.clone()
-            } else {
-                metadata.guest.clone()
-            };
-            let _ = tx.send(Event::new(
-                EventKind::GameEnd,
-                EventData::GameEnd {
-                    winner: helpers::get_user(state, &winner, false)
-                        .await
-                        .unwrap()
-                        .username,
-                    points: black.max(white),
-                    total: black + white,
-                },
-            ));
-            let mut model = metadata.clone().into_active_model();
-            model.ended = ActiveValue::set(true);
-            let _ = model.save(state.database.as_ref()).await;
-        }
-        Ok(res)
+        let (metadata, piece) = self.ensure_color(state, id, *piece).await?;
+        play(state, &metadata, *x, *y, piece).await
     }
 
     async fn preview(&self, state: &AppState) -> Result<Event, Event> {
@@ -244,15 +272,15 @@ impl Packet {
             panic!("expected serde to reject invalid packet data")
         };
         // Verify that the authenticated user is either the host or guest of the game.
-        self.ensure_participant(state, id).await?;
-        let mut games = state.games.lock().expect("mutex was poisoned");
+        let (_, piece) = self.ensure_color(state, id, *piece).await?;
+        let games = state.games.lock().expect("mutex was poisoned");
         let uuid = Uuid::from_str(id)
             .map_err(|_| Event::error(strings::INVALID_GAME_ID_FORMAT, StatusCode::BAD_REQUEST))?;
-        let game = games.get_mut(&uuid).ok_or(Event::error(
+        let game = games.get(&uuid).ok_or(Event::error(
             strings::INVALID_GAME_ID,
             StatusCode::NOT_FOUND,
         ))?;
-        game.preview(*x, *y, *piece).map_or_else(
+        game.preview(*x, *y, piece).map_or_else(
             |e| Err(Event::error(&e.to_string(), StatusCode::BAD_REQUEST)),
             |changed| {
                 Ok(Event::new(
@@ -264,6 +292,653 @@ impl Packet {
     }
 }
 
+impl Packet {
+    /// Asks the opponent to let the authenticated user take back their last move. The bot
+    /// always agrees, so takebacks in games against it happen right away.
+    async fn request_takeback(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::Takeback { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        if metadata.ended {
+            return Err(Event::error(strings::GAME_OVER, StatusCode::BAD_REQUEST));
+        }
+        // Don't bother the opponent with a request that couldn't be carried out.
+        {
+            let games = state.games.lock().expect("mutex was poisoned");
+            let game = games.get(&metadata.id).ok_or(Event::error(
+                strings::INVALID_GAME_ID,
+                StatusCode::NOT_FOUND,
+            ))?;
+            rewind(game, piece)
+                .ok_or(Event::error(strings::NO_TAKEBACK, StatusCode::BAD_REQUEST))?;
+        }
+        if bot::difficulty(&metadata).is_some() {
+            return takeback(state, &metadata, piece).await;
+        }
+        state
+            .takebacks
+            .lock()
+            .expect("mutex was poisoned")
+            .insert(metadata.id, piece);
+        broadcast(
+            state,
+            metadata.id,
+            Event::new(
+                EventKind::TakebackRequest,
+                EventData::TakebackRequest { piece },
+            ),
+        );
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+
+    /// Accepts or declines the opponent's takeback request.
+    async fn answer_takeback(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::AnswerTakeback { id, accept } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        let requester = answer(&state.takebacks, metadata.id, piece).ok_or(Event::error(
+            strings::NO_TAKEBACK_REQUEST,
+            StatusCode::BAD_REQUEST,
+        ))?;
+        if *accept {
+            takeback(state, &metadata, requester).await
+        } else {
+            broadcast(
+                state,
+                metadata.id,
+                Event::new(EventKind::TakebackDecline, EventData::TakebackDecline),
+            );
+            Ok(Event::new(EventKind::Ack, EventData::Ack))
+        }
+    }
+
+    /// Ends the game with a loss for the authenticated user.
+    async fn resign(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::Resign { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        let game = current(state, &metadata)?;
+        conclude(
+            state,
+            &metadata,
+            game.forfeit(Reason::Resignation, Some(piece)),
+        )
+        .await?;
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+
+    /// Offers the opponent a draw. The bot always plays on, so it declines right away.
+    async fn offer_draw(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::OfferDraw { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        current(state, &metadata)?;
+        let event = if bot::difficulty(&metadata).is_some() {
+            Event::new(EventKind::DrawDecline, EventData::DrawDecline)
+        } else {
+            state
+                .draws
+                .lock()
+                .expect("mutex was poisoned")
+                .insert(metadata.id, piece);
+            Event::new(EventKind::DrawOffer, EventData::DrawOffer { piece })
+        };
+        broadcast(state, metadata.id, event);
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+
+    /// Accepts the opponent's draw offer, ending the game in a draw.
+    async fn accept_draw(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::AcceptDraw { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        let game = current(state, &metadata)?;
+        answer(&state.draws, metadata.id, piece).ok_or(Event::error(
+            strings::NO_DRAW_OFFER,
+            StatusCode::BAD_REQUEST,
+        ))?;
+        conclude(state, &metadata, game.forfeit(Reason::Agreement, None)).await?;
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+
+    /// Declines the opponent's draw offer, so the game goes on.
+    async fn decline_draw(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::DeclineDraw { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        answer(&state.draws, metadata.id, piece).ok_or(Event::error(
+            strings::NO_DRAW_OFFER,
+            StatusCode::BAD_REQUEST,
+        ))?;
+        broadcast(
+            state,
+            metadata.id,
+            Event::new(EventKind::DrawDecline, EventData::DrawDecline),
+        );
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+}
+
+impl Packet {
+    /// Subscribes a read-only socket to the room of a game that the authenticated user is
+    /// allowed to watch. Everyone in the room is told how many spectators there are whenever
+    /// one arrives or leaves.
+    async fn spectate(
+        &self,
+        state: &Arc<AppState>,
+        sender: mpsc::Sender<Event>,
+    ) -> Result<Event, Event> {
+        let Data::Spectate { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let user = self.current_user(state).await?;
+        let metadata = self.game(state, id).await?;
+        if helpers::piece(&metadata, &user).is_some() {
+            return Err(Event::error(
+                strings::SPECTATE_OWN_GAME,
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        let allowed = helpers::can_spectate(state, &metadata, &user)
+            .await
+            .map_err(|StringError(message, code)| Event::error(&message, code))?;
+        let not_found = || Event::error(strings::INVALID_GAME_ID, StatusCode::NOT_FOUND);
+        if !allowed {
+            return Err(not_found());
+        }
+        let uuid = metadata.id;
+        let game = {
+            let games = state.games.lock().expect("mutex was poisoned");
+            games.get(&uuid).cloned().ok_or_else(not_found)?
+        };
+        let rx = {
+            let rooms = state.rooms.lock().expect("mutex was poisoned");
+            rooms.get(&uuid).map(broadcast::Sender::subscribe)
+        };
+        let rx = rx.ok_or_else(not_found)?;
+        state
+            .spectators
+            .lock()
+            .expect("mutex was poisoned")
+            .entry(uuid)
+            .or_default()
+            .push((user, sender.clone()));
+        forward(state, uuid, None, rx, sender.clone());
+        announce_spectators(state, uuid);
+        // Tell the room again once the spectator's socket goes away.
+        let watched = Arc::clone(state);
+        tokio::spawn(async move {
+            sender.closed().await;
+            announce_spectators(&watched, uuid);
+        });
+        Ok(update(state, uuid, &game))
+    }
+}
+
+impl Packet {
+    /// Sends a message to everyone in the game's room and saves it so that it can be read
+    /// back after the game. Only players can chat, only while the game is being played, and
+    /// only a few messages at a time.
+    async fn chat(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::Chat { id, message } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        let error = |StringError(message, code)| Event::error(&message, code);
+        let message = chat::validate(message).map_err(error)?;
+        // Mutes and rate limits are forgotten once the game ends, so the chat closes with it.
+        current(state, &metadata)?;
+        if !chat::allow(state, metadata.id, piece) {
+            return Err(Event::error(
+                strings::CHAT_RATE_LIMITED,
+                StatusCode::TOO_MANY_REQUESTS,
+            ));
+        }
+        let user = self.current_user(state).await?;
+        let author = helpers::get_user(state, &user, false)
+            .await
+            .map_err(error)?
+            .username;
+        chat::record(state, metadata.id, &user, piece, message)
+            .await
+            .map_err(error)?;
+        broadcast(
+            state,
+            metadata.id,
+            Event::new(
+                EventKind::ChatMessage,
+                EventData::ChatMessage {
+                    author,
+                    piece,
+                    message: message.to_string(),
+                },
+            ),
+        );
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+
+    /// Stops the opponent's chat messages from reaching the authenticated user for the rest
+    /// of the game. The opponent isn't told.
+    async fn mute(&self, state: &AppState) -> Result<Event, Event> {
+        let Data::Mute { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        current(state, &metadata)?;
+        chat::mute(state, metadata.id, piece);
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+}
+
+/// Tells the room of the game with ID `uuid` how many spectators it has.
+fn announce_spectators(state: &AppState, uuid: Uuid) {
+    let spectators = spectators(state, uuid);
+    broadcast(
+        state,
+        uuid,
+        Event::new(
+            EventKind::SpectatorCount,
+            EventData::SpectatorCount { spectators },
+        ),
+    );
+}
+
+impl Packet {
+    /// Offers the opponent a rematch of a finished game, with the colors swapped. The bot
+    /// always accepts, so rematches against it start right away.
+    async fn rematch(&self, state: &Arc<AppState>) -> Result<Event, Event> {
+        let Data::Rematch { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let (metadata, piece) = self.ensure_color(state, id, None).await?;
+        if !metadata.ended {
+            return Err(Event::error(
+                strings::GAME_NOT_OVER,
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        let error = |StringError(message, code)| Event::error(&message, code);
+        let user = self.current_user(state).await?;
+        let created = rematch::create(state, &metadata, &user)
+            .await
+            .map_err(error)?;
+        if bot::difficulty(&metadata).is_some() {
+            rematch::start(state, &created).await.map_err(error)?;
+        } else {
+            let offer = Event::new(
+                EventKind::RematchOffer,
+                EventData::RematchOffer {
+                    id: created.id,
+                    piece,
+                },
+            );
+            rematch::notify(state, metadata.id, offer).await;
+        }
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+
+    /// Accepts a rematch offered by the opponent, starting it with both players' sockets.
+    async fn accept_rematch(&self, state: &Arc<AppState>) -> Result<Event, Event> {
+        let Data::AcceptRematch { id } = &self.d else {
+            panic!("expected serde to reject invalid packet data")
+        };
+        let user = self.current_user(state).await?;
+        let metadata = self.game(state, id).await?;
+        // Only the player who was offered the rematch can accept it, and only once.
+        if metadata.rematch_of.is_none() || !metadata.pending || metadata.guest != user {
+            return Err(Event::error(strings::NO_REMATCH, StatusCode::BAD_REQUEST));
+        }
+        let mut active = metadata.clone().into_active_model();
+        active.pending = ActiveValue::set(false);
+        let metadata = active
+            .update(state.database.as_ref())
+            .await
+            .map_err(|e| Event::error(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+        rematch::start(state, &metadata)
+            .await
+            .map_err(|StringError(message, code)| Event::error(&message, code))?;
+        Ok(Event::new(EventKind::Ack, EventData::Ack))
+    }
+}
+
+/// Forwards every event in the room of the game with ID `uuid` to `sender`, a socket of the
+/// player of `piece`, and remembers the sender so that it can be moved into a rematch. Returns
+/// `false` if the game has no room.
+///
+/// A rematch can be offered after the game ends, so the sender is remembered until the rematch
+/// starts, the game is left or the socket closes, whichever comes first.
+pub fn subscribe(state: &AppState, uuid: Uuid, piece: Piece, sender: mpsc::Sender<Event>) -> bool {
+    let rx = {
+        let rooms = state.rooms.lock().expect("mutex was poisoned");
+        rooms
+            .get(&uuid)
+            .map(tokio::sync::broadcast::Sender::subscribe)
+    };
+    let Some(rx) = rx else {
+        return false;
+    };
+    state
+        .sockets
+        .lock()
+        .expect("mutex was poisoned")
+        .entry(uuid)
+        .or_default()
+        .push((piece, sender.clone()));
+    forward(state, uuid, Some(piece), rx, sender.clone());
+    let sockets = Arc::clone(&state.sockets);
+    tokio::spawn(async move {
+        sender.closed().await;
+        let mut sockets = sockets.lock().expect("mutex was poisoned");
+        if let Some(senders) = sockets.get_mut(&uuid) {
+            senders.retain(|(_, sender)| !sender.is_closed());
+            if senders.is_empty() {
+                sockets.remove(&uuid);
+            }
+        }
+    });
+    true
+}
+
+/// Spawns a task to pass every event from the room of the game with ID `uuid` on to a socket's
+/// `sender`. Sockets of the player of `viewer` skip their opponent's chat messages once that
+/// player has muted them, while spectators see everything.
+fn forward(
+    state: &AppState,
+    uuid: Uuid,
+    viewer: Option<Piece>,
+    mut rx: broadcast::Receiver<Event>,
+    sender: mpsc::Sender<Event>,
+) {
+    let mutes = Arc::clone(&state.mutes);
+    tokio::spawn(async move {
+        while let Ok(update) = rx.recv().await {
+            if let (EventData::ChatMessage { piece, .. }, Some(viewer)) = (&update.d, viewer) {
+                if *piece != viewer && chat::muted(&mutes, uuid, viewer) {
+                    continue;
+                }
+            }
+            let _ = sender.send(update).await;
+        }
+    });
+}
+
+/// How many sockets are watching the game with ID `uuid`, not counting its players.
+/// # Panics
+/// Panics if the mutex is poisoned.
+pub fn spectators(state: &AppState, uuid: Uuid) -> usize {
+    let mut spectators = state.spectators.lock().expect("mutex was poisoned");
+    let Some(watching) = spectators.get_mut(&uuid) else {
+        return 0;
+    };
+    watching.retain(|(_, sender)| !sender.is_closed());
+    let count = watching.len();
+    if count == 0 {
+        spectators.remove(&uuid);
+    }
+    count
+}
+
+/// Takes the pending request in `pending` for the game with ID `uuid` so that the player of
+/// `piece` can answer it. Only the opponent of whoever asked can answer, and only once.
+fn answer(pending: &Mutex<HashMap<Uuid, Piece>>, uuid: Uuid, piece: Piece) -> Option<Piece> {
+    let mut pending = pending.lock().expect("mutex was poisoned");
+    match pending.get(&uuid) {
+        Some(&requester) if requester != piece => pending.remove(&uuid),
+        _ => None,
+    }
+}
+
+/// A copy of the game described by `metadata` as it's being played, as long as it hasn't
+/// ended.
+fn current(state: &AppState, metadata: &game::Model) -> Result<Game, Event> {
+    if metadata.ended {
+        return Err(Event::error(strings::GAME_OVER, StatusCode::BAD_REQUEST));
+    }
+    let games = state.games.lock().expect("mutex was poisoned");
+    games.get(&metadata.id).cloned().ok_or(Event::error(
+        strings::INVALID_GAME_ID,
+        StatusCode::NOT_FOUND,
+    ))
+}
+
+/// Places `piece` at (x, y) in the game described by `metadata`, broadcasting the update to
+/// the game's room and ending the game once neither player can move. Moves made by players and
+/// by the bot both go through here. No moves can be made once the game has ended, even if it's
+/// still in memory.
+pub async fn play(
+    state: &AppState,
+    metadata: &game::Model,
+    x: usize,
+    y: usize,
+    piece: Piece,
+) -> Result<Event, Event> {
+    if metadata.ended {
+        return Err(Event::error(strings::GAME_OVER, StatusCode::BAD_REQUEST));
+    }
+    let uuid = metadata.id;
+    // A move that arrives after the mover's time has run out loses on time instead.
+    if let Some(loser) = clock::expire(state, uuid) {
+        let game = current(state, metadata)?;
+        conclude(state, metadata, game.forfeit(Reason::Timeout, Some(loser))).await?;
+        let message = if loser == piece {
+            strings::OUT_OF_TIME
+        } else {
+            strings::GAME_OVER
+        };
+        return Err(Event::error(message, StatusCode::BAD_REQUEST));
+    }
+    let tx = {
+        let mut rooms = state.rooms.lock().expect("mutex was poisoned");
+        rooms
+            .get_mut(&uuid)
+            .ok_or(Event::error(
+                strings::INVALID_GAME_ID,
+                StatusCode::NOT_FOUND,
+            ))?
+            .clone()
+    };
+    let (res, game, ply, flips) = {
+        let mut games = state.games.lock().expect("mutex was poisoned");
+        let game = games.get_mut(&uuid).ok_or(Event::error(
+            strings::INVALID_GAME_ID,
+            StatusCode::NOT_FOUND,
+        ))?;
+        let ply = game.history().len();
+        let flips = game.preview(x, y, piece).map_or(0, |flips| flips.len());
+        let res = game.place(x, y, piece).map_or_else(
+            |e| Err(Event::error(&e.to_string(), StatusCode::BAD_REQUEST)),
+            |()| Ok(Event::new(EventKind::Ack, EventData::Ack)),
+        )?;
+        clock::press(state, uuid, game.turn(), true);
+        let _ = tx.send(update(state, uuid, game));
+        cache(state, uuid, game);
+        (res, game.clone(), ply, flips)
+    };
+    // Takeback requests and draw offers are about the position they were made in, so they
+    // lapse once the game moves on.
+    for pending in [&state.takebacks, &state.draws] {
+        pending.lock().expect("mutex was poisoned").remove(&uuid);
+    }
+    // The move has already been made, so a failure to record it is only worth reporting.
+    if let Err(StringError(message, _)) =
+        helpers::record_move(state, uuid, ply, (x, y), piece, flips).await
+    {
+        log::error!("failed to record move {ply} of {uuid}: {message}");
+    }
+    // Correspondence games give the next player a fresh deadline for their move.
+    if let Err(StringError(message, _)) = helpers::reset_deadline(state, metadata).await {
+        log::error!("failed to reset the deadline of {uuid}: {message}");
+    }
+    // `Game::place` passes for a player who is left without a move, so this only
+    // holds once neither player can move.
+    if let Some(result) = game.result() {
+        finish(state, metadata, result).await?;
+    }
+    Ok(res)
+}
+
+/// Ends the game described by `metadata` with `result` before the board has decided it, such
+/// as by resignation. The game stays in the database, but is taken out of play so that no
+/// more moves can be made in it.
+pub async fn conclude(
+    state: &AppState,
+    metadata: &game::Model,
+    result: GameResult,
+) -> Result<(), Event> {
+    finish(state, metadata, result).await?;
+    let uuid = metadata.id;
+    state
+        .games
+        .lock()
+        .expect("mutex was poisoned")
+        .remove(&uuid);
+    // Dropping the room closes it once everyone has been told that the game ended.
+    state
+        .rooms
+        .lock()
+        .expect("mutex was poisoned")
+        .remove(&uuid);
+    for pending in [&state.takebacks, &state.draws] {
+        pending.lock().expect("mutex was poisoned").remove(&uuid);
+    }
+    Ok(())
+}
+
+/// Takes back `piece`'s last move in the game described by `metadata`, along with every move
+/// and pass made since, and broadcasts the rewound game to its room. This is one ply if the
+/// opponent hasn't replied yet, or two if they have.
+pub async fn takeback(
+    state: &AppState,
+    metadata: &game::Model,
+    piece: Piece,
+) -> Result<Event, Event> {
+    let uuid = metadata.id;
+    if metadata.ended {
+        return Err(Event::error(strings::GAME_OVER, StatusCode::BAD_REQUEST));
+    }
+    // Broadcasting locks the rooms, which `leave` locks before the games, so the games guard
+    // has to be dropped first.
+    let (game, event) = {
+        let mut games = state.games.lock().expect("mutex was poisoned");
+        let game = games.get_mut(&uuid).ok_or(Event::error(
+            strings::INVALID_GAME_ID,
+            StatusCode::NOT_FOUND,
+        ))?;
+        *game = rewind(game, piece)
+            .ok_or(Event::error(strings::NO_TAKEBACK, StatusCode::BAD_REQUEST))?;
+        clock::press(state, uuid, game.turn(), false);
+        (game.clone(), update(state, uuid, game))
+    };
+    broadcast(state, uuid, event);
+    cache(state, uuid, &game);
+    let ply = game.history().len();
+    state
+        .takebacks
+        .lock()
+        .expect("mutex was poisoned")
+        .remove(&uuid);
+    // The game has already been rewound, so a failure to forget the moves is only worth
+    // reporting.
+    if let Err(StringError(message, _)) = helpers::delete_moves(state, uuid, ply).await {
+        log::error!("failed to delete moves of {uuid} from {ply}: {message}");
+    }
+    Ok(Event::new(EventKind::Ack, EventData::Ack))
+}
+
+/// `game` as it was before `piece`'s last move, or `None` if `piece` hasn't moved yet.
+fn rewind(game: &Game, piece: Piece) -> Option<Game> {
+    let mut game = game.clone();
+    loop {
+        let step = game.undo()?;
+        if step.piece == piece && step.square.is_some() {
+            return Some(game);
+        }
+    }
+}
+
+/// An update with `game` as it now stands and the time left on its clock, if it has one.
+pub fn update(state: &AppState, uuid: Uuid, game: &Game) -> Event {
+    Event::new(
+        EventKind::GameUpdate,
+        EventData::GameUpdate {
+            game: Box::new(game.clone()),
+            clock: clock::times(state, uuid),
+        },
+    )
+}
+
+/// Sends `event` to everyone in the room of the game with ID `uuid`, if it has one.
+fn broadcast(state: &AppState, uuid: Uuid, event: Event) {
+    let tx = {
+        let rooms = state.rooms.lock().expect("mutex was poisoned");
+        rooms.get(&uuid).cloned()
+    };
+    if let Some(tx) = tx {
+        let _ = tx.send(event);
+    }
+}
+
+/// Saves `game` to Redis so that it survives a restart.
+fn cache(state: &AppState, uuid: Uuid, game: &Game) {
+    if let Ok(mut conn) = state.redis.get_connection() {
+        let _ = conn.set::<String, String, String>(
+            format!("game:{uuid}"),
+            serde_json::to_string(game).unwrap(),
+        );
+    }
+}
+
+/// Records `result` as the end of the game described by `metadata`, stops its clock, forgets
+/// its chat mutes and rate limits, and announces it to the game's room.
+pub async fn finish(
+    state: &AppState,
+    metadata: &game::Model,
+    result: GameResult,
+) -> Result<(), Event> {
+    state
+        .clocks
+        .lock()
+        .expect("mutex was poisoned")
+        .remove(&metadata.id);
+    chat::forget(state, metadata.id);
+    let (black, white) = helpers::players(metadata);
+    let winner = result.winner.map(|winner| match winner {
+        Piece::Black => black.to_string(),
+        Piece::White => white.to_string(),
+    });
+    let mut model = metadata.clone().into_active_model();
+    model.ended = ActiveValue::set(true);
+    model.winner = ActiveValue::set(winner.clone());
+    model.black_score = ActiveValue::set(i32::try_from(result.score.0).ok());
+    model.white_score = ActiveValue::set(i32::try_from(result.score.1).ok());
+    model.reason = ActiveValue::set(helpers::column(result.reason));
+    model
+        .save(state.database.as_ref())
+        .await
+        .map_err(|e| Event::error(&e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))?;
+    let winner = match winner {
+        Some(winner) => Some(
+            helpers::get_user(state, &winner, false)
+                .await
+                .map_err(|StringError(message, code)| Event::error(&message, code))?
+                .username,
+        ),
+        None => None,
+    };
+    broadcast(
+        state,
+        metadata.id,
+        Event::new(EventKind::GameEnd, EventData::GameEnd { winner, result }),
+    );
+    Ok(())
+}
+
 // Middleware to require authentication for chosen Packet types.
 impl Packet {
     async fn authenticated<'a, F>(
@@ -312,13 +987,42 @@ impl Packet {
         let user = self.current_user(state).await?;
         let game = self.game(state, id).await?;
         if game.host != user && game.guest != user {
-            return Err(Event::error(
-                strings::INVALID_GAME_ID,
-                StatusCode::NOT_FOUND,
-            ));
+            return Err(outsider(state, game.id, &user));
         }
         Ok(())
     }
+
+    /// Finds the color the authenticated user plays in the game, checking it against the
+    /// color the client claimed to be playing, if any.
+    async fn ensure_color(
+        &self,
+        state: &AppState,
+        id: &str,
+        claimed: Option<Piece>,
+    ) -> Result<(game::Model, Piece), Event> {
+        let user = self.current_user(state).await?;
+        let game = self.game(state, id).await?;
+        let piece = helpers::piece(&game, &user).ok_or_else(|| outsider(state, game.id, &user))?;
+        if claimed.is_some_and(|claimed| claimed != piece) {
+            return Err(Event::error(strings::WRONG_COLOR, StatusCode::FORBIDDEN));
+        }
+        Ok((game, piece))
+    }
+}
+
+/// The error for the member with ID `user` trying to act in the game with ID `uuid` without
+/// playing in it. Spectators are told that they can only watch, while anyone else is told
+/// that the game doesn't exist.
+fn outsider(state: &AppState, uuid: Uuid, user: &str) -> Event {
+    let spectators = state.spectators.lock().expect("mutex was poisoned");
+    let spectating = spectators
+        .get(&uuid)
+        .is_some_and(|spectators| spectators.iter().any(|(member, _)| member == user));
+    if spectating {
+        Event::error(strings::SPECTATOR, StatusCode::FORBIDDEN)
+    } else {
+        Event::error(strings::INVALID_GAME_ID, StatusCode::NOT_FOUND)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -337,6 +1041,14 @@ pub enum EventKind {
     GameUpdatePreview,
     Error,
     GameEnd,
+    TakebackRequest,
+    TakebackDecline,
+    DrawOffer,
+    DrawDecline,
+    RematchOffer,
+    RematchStart,
+    SpectatorCount,
+    ChatMessage,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -348,21 +1060,71 @@ pub enum EventData {
         id: String,
     },
     GameUpdate {
-        game: Game,
+        // Boxed so that every other event doesn't have to make room for a whole game.
+        game: Box<Game>,
+        /// The time each player has left, in games with a time control.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        clock: Option<clock::Times>,
     },
     GameUpdatePreview {
         changed: Vec<(usize, usize)>,
     },
     GameAbort,
     GameEnd {
-        winner: String,
-        points: usize,
-        total: usize,
+        /// The username of the winner, or `None` for a draw.
+        winner: Option<String>,
+        result: GameResult,
     },
     Error {
         message: String,
         code: u16,
     },
+    TakebackRequest {
+        /// The color of the player who wants to take back their last move.
+        piece: Piece,
+    },
+    TakebackDecline,
+    DrawOffer {
+        /// The color of the player offering the draw.
+        piece: Piece,
+    },
+    DrawDecline,
+    RematchOffer {
+        /// The ID of the rematch, which is accepted with its own ID.
+        id: Uuid,
+        /// The color the player offering the rematch played in the finished game.
+        piece: Piece,
+    },
+    RematchStart {
+        /// The ID of the rematch, whose room the socket has been moved into.
+        id: Uuid,
+    },
+    SpectatorCount {
+        spectators: usize,
+    },
+    ChatMessage {
+        /// The username of the player who sent the message.
+        author: String,
+        /// The color of the player who sent the message.
+        piece: Piece,
+        message: String,
+    },
+}
+
+/// Error events from moves made over HTTP are reported as ordinary errors.
+impl From<Event> for StringError {
+    fn from(event: Event) -> Self {
+        match event.d {
+            EventData::Error { message, code } => Self(
+                message,
+                StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            ),
+            _ => Self(
+                strings::BAD_REQUEST.to_string(),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        }
+    }
 }
 
 impl Event {
@@ -384,3 +1146,800 @@ impl Event {
         &self.d
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{EventData, EventKind, Packet};
+    use crate::server::entities::{game, prelude::Game as GameModel};
+    use crate::{
+        server::{self, bot::BOT_ID, helpers, strings},
+        GameResult, Piece, Reason,
+    };
+    use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+    use std::{sync::Arc, time::Duration};
+    use test_utils::{function, Client, Map};
+
+    #[tokio::test]
+    async fn colors() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 201);
+        assert_eq!(res["message"]["black"], res["message"]["host"]);
+        assert_eq!(res["message"]["white"], res["message"]["guest"]);
+        let id = res["message"]["id"].as_str().unwrap();
+        let user = helpers::get_user(&state, &function!(), true).await.unwrap();
+        let token = helpers::create_session(&state, &user, function!())
+            .await
+            .unwrap();
+        let place = |piece: serde_json::Value| -> Packet {
+            serde_json::from_value(serde_json::json!({
+                "op": 2,
+                "t": token,
+                "d": { "type": "Place", "id": id, "x": 5, "y": 4, "piece": piece },
+            }))
+            .unwrap()
+        };
+        // The host plays Black, so claiming to be White is rejected.
+        let event = place("White".into()).process(&state, None).await;
+        assert!(matches!(event.data(), EventData::Error { code: 403, .. }));
+        // Leaving the color out lets the server work it out from the session.
+        let event = place(serde_json::Value::Null).process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+    }
+
+    #[tokio::test]
+    async fn results() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "white" });
+        let mut games = vec![];
+        for _ in 0..2 {
+            let res: Map = client.post(&url, "/game", &body).await;
+            games.push(res["message"]["id"].as_str().unwrap().to_string());
+        }
+        // A drawn game has no winner, rather than going to either player.
+        let metadata = helpers::get_game(&state, &games[0]).await.unwrap();
+        let result = GameResult::played((32, 32), Reason::BoardFull);
+        super::finish(&state, &metadata, result).await.unwrap();
+        let res: Map = client.get(&url, &format!("/game/{}", games[0])).await;
+        assert_eq!(res["message"]["ended"], true);
+        assert_eq!(res["message"]["winner"], serde_json::Value::Null);
+        assert_eq!(res["message"]["score"], serde_json::json!([32, 32]));
+        assert_eq!(res["message"]["reason"], "board_full");
+        // The host plays White here, so Black losing means the host won.
+        let metadata = helpers::get_game(&state, &games[1]).await.unwrap();
+        let result = crate::Game::new().forfeit(Reason::Resignation, Some(Piece::Black));
+        super::finish(&state, &metadata, result).await.unwrap();
+        let res: Map = client.get(&url, &format!("/game/{}", games[1])).await;
+        assert_eq!(res["message"]["winner"], metadata.host);
+        assert_ne!(res["message"]["winner"], BOT_ID.to_string());
+        assert_eq!(res["message"]["reason"], "resignation");
+    }
+
+    #[tokio::test]
+    async fn history() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap();
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        super::play(&state, &metadata, 5, 4, Piece::Black)
+            .await
+            .unwrap();
+        // Wait for the bot's reply to be recorded as well.
+        let mut moves = vec![];
+        for _ in 0..100 {
+            moves = helpers::get_moves(&state, metadata.id).await.unwrap();
+            if moves.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(moves.len(), 2);
+        assert_eq!((moves[0].ply, moves[0].x, moves[0].y), (0, 5, 4));
+        assert_eq!(moves[0].color, "Black");
+        assert_eq!(moves[0].flips, 1);
+        assert_eq!((moves[1].ply, moves[1].color.as_str()), (1, "White"));
+        // The moves are enough to rebuild the game without the in-memory copy.
+        let game = state.games.lock().unwrap()[&metadata.id].clone();
+        let replayed = helpers::replay(&state, &metadata).await.unwrap();
+        assert_eq!(replayed, game);
+    }
+
+    #[tokio::test]
+    async fn takebacks() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let body = serde_json::json!({ "guest": guest, "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let res: Map = other
+            .post(
+                &url,
+                &format!("/@me/games/{id}/accept"),
+                &serde_json::json!({}),
+            )
+            .await;
+        assert_eq!(res["code"], 200);
+        let token = |name: String| {
+            let state = Arc::clone(&state);
+            async move {
+                let user = helpers::get_user(&state, &name, true).await.unwrap();
+                helpers::create_session(&state, &user, name).await.unwrap()
+            }
+        };
+        let (black, white) = (token(host).await, token(guest).await);
+        let packet = |op: u8, token: &str, d: serde_json::Value| -> Packet {
+            serde_json::from_value(serde_json::json!({ "op": op, "t": token, "d": d })).unwrap()
+        };
+        let request = serde_json::json!({ "type": "Takeback", "id": id });
+        let answer =
+            |accept| serde_json::json!({ "type": "AnswerTakeback", "id": id, "accept": accept });
+        // There's nothing to take back before the first move.
+        let event = packet(8, &black, request.clone())
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        super::play(&state, &metadata, 5, 4, Piece::Black)
+            .await
+            .unwrap();
+        super::play(&state, &metadata, 5, 5, Piece::White)
+            .await
+            .unwrap();
+        let mut rx = state.rooms.lock().unwrap()[&metadata.id].subscribe();
+        // Black asks to take back f5, which also takes back White's reply.
+        let event = packet(8, &black, request.clone())
+            .process(&state, None)
+            .await;
+        assert_eq!(event.op, EventKind::Ack);
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.op, EventKind::TakebackRequest);
+        assert!(matches!(
+            event.data(),
+            EventData::TakebackRequest {
+                piece: Piece::Black
+            }
+        ));
+        // Only the opponent can answer.
+        let event = packet(9, &black, answer(true)).process(&state, None).await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        let event = packet(9, &white, answer(false)).process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+        assert_eq!(rx.recv().await.unwrap().op, EventKind::TakebackDecline);
+        // A declined request can't be accepted afterwards.
+        let event = packet(9, &white, answer(true)).process(&state, None).await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        packet(8, &black, request.clone())
+            .process(&state, None)
+            .await;
+        rx.recv().await.unwrap();
+        let event = packet(9, &white, answer(true)).process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+        assert_eq!(rx.recv().await.unwrap().op, EventKind::GameUpdate);
+        let game = state.games.lock().unwrap()[&metadata.id].clone();
+        assert_eq!(game, crate::Game::new());
+        assert!(helpers::get_moves(&state, metadata.id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn bot_takebacks() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let client = Client::authenticated(&[&function!()], &url, true).await;
+        let body = serde_json::json!({ "bot": "beginner", "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap();
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        super::play(&state, &metadata, 5, 4, Piece::Black)
+            .await
+            .unwrap();
+        let user = helpers::get_user(&state, &function!(), true).await.unwrap();
+        let token = helpers::create_session(&state, &user, function!())
+            .await
+            .unwrap();
+        let packet: Packet = serde_json::from_value(serde_json::json!({
+            "op": 8,
+            "t": token,
+            "d": { "type": "Takeback", "id": id },
+        }))
+        .unwrap();
+        // The bot agrees right away, whether or not it has replied yet.
+        let event = packet.process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+        let game = state.games.lock().unwrap()[&metadata.id].clone();
+        assert_eq!(game, crate::Game::new());
+    }
+
+    #[tokio::test]
+    async fn endings() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let mut games = vec![];
+        for _ in 0..2 {
+            let body = serde_json::json!({ "guest": guest, "color": "black" });
+            let res: Map = client.post(&url, "/game", &body).await;
+            let id = res["message"]["id"].as_str().unwrap().to_string();
+            let endpoint = format!("/@me/games/{id}/accept");
+            let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+            games.push(id);
+        }
+        let token = |name: String| {
+            let state = Arc::clone(&state);
+            async move {
+                let user = helpers::get_user(&state, &name, true).await.unwrap();
+                helpers::create_session(&state, &user, name).await.unwrap()
+            }
+        };
+        let (black, white) = (token(host).await, token(guest).await);
+        let packet = |op: u8, token: &str, kind: &str, id: &str| -> Packet {
+            serde_json::from_value(serde_json::json!({
+                "op": op,
+                "t": token,
+                "d": { "type": kind, "id": id },
+            }))
+            .unwrap()
+        };
+        // White resigns, which Black wins, and the game is kept rather than deleted.
+        let event = packet(10, &white, "Resign", &games[0])
+            .process(&state, None)
+            .await;
+        assert_eq!(event.op, EventKind::Ack);
+        let metadata = helpers::get_game(&state, &games[0]).await.unwrap();
+        assert!(metadata.ended);
+        assert_eq!(
+            metadata.winner.as_deref(),
+            Some(helpers::players(&metadata).0)
+        );
+        assert_eq!(metadata.reason.as_deref(), Some("resignation"));
+        let event = packet(10, &black, "Resign", &games[0])
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        // Black offers a draw, which White declines before accepting a second offer.
+        let id = &games[1];
+        let uuid = uuid::Uuid::parse_str(id).unwrap();
+        let mut rx = state.rooms.lock().unwrap()[&uuid].subscribe();
+        let event = packet(12, &white, "AcceptDraw", id)
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        packet(11, &black, "OfferDraw", id)
+            .process(&state, None)
+            .await;
+        assert_eq!(rx.recv().await.unwrap().op, EventKind::DrawOffer);
+        let event = packet(12, &black, "AcceptDraw", id)
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        packet(13, &white, "DeclineDraw", id)
+            .process(&state, None)
+            .await;
+        assert_eq!(rx.recv().await.unwrap().op, EventKind::DrawDecline);
+        packet(11, &black, "OfferDraw", id)
+            .process(&state, None)
+            .await;
+        rx.recv().await.unwrap();
+        let event = packet(12, &white, "AcceptDraw", id)
+            .process(&state, None)
+            .await;
+        assert_eq!(event.op, EventKind::Ack);
+        let event = rx.recv().await.unwrap();
+        assert!(matches!(
+            event.data(),
+            EventData::GameEnd {
+                winner: None,
+                result: GameResult {
+                    reason: Reason::Agreement,
+                    ..
+                }
+            }
+        ));
+        let metadata = helpers::get_game(&state, id).await.unwrap();
+        assert!(metadata.ended);
+        assert_eq!(metadata.winner, None);
+        assert_eq!(metadata.reason.as_deref(), Some("agreement"));
+        assert_eq!(
+            (metadata.black_score, metadata.white_score),
+            (Some(2), Some(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn ended() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let body = serde_json::json!({ "guest": guest, "color": "black" });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let endpoint = format!("/@me/games/{id}/accept");
+        let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+        let user = helpers::get_user(&state, &host, true).await.unwrap();
+        let token = helpers::create_session(&state, &user, host).await.unwrap();
+        let packet = |op: u8, d: serde_json::Value| -> Packet {
+            serde_json::from_value(serde_json::json!({ "op": op, "t": token, "d": d })).unwrap()
+        };
+        let resign = serde_json::json!({ "type": "Resign", "id": id });
+        let event = packet(10, resign).process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+        // Bring the resigned game back into memory, the way a restart used to.
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        let game = helpers::replay(&state, &metadata).await.unwrap();
+        server::insert_in_memory_game(&state, metadata.id, game);
+        let place = serde_json::json!({ "type": "Place", "id": id, "x": 5, "y": 4 });
+        let event = packet(2, place).process(&state, None).await;
+        assert!(matches!(
+            event.data(),
+            EventData::Error { message, code: 400 } if message == strings::GAME_OVER
+        ));
+        // The resignation stands rather than being replaced by a played-out result.
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        assert_eq!(metadata.reason.as_deref(), Some("resignation"));
+        assert_eq!(state.games.lock().unwrap()[&metadata.id].history().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn clocks() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        // A time control has to give players some time.
+        let body = serde_json::json!({
+            "guest": guest,
+            "time_control": { "type": "per_move", "seconds": 0 },
+        });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 400);
+        let body = serde_json::json!({
+            "guest": guest,
+            "color": "black",
+            "time_control": { "type": "per_move", "seconds": 1 },
+        });
+        let res: Map = client.post(&url, "/game", &body).await;
+        assert_eq!(res["code"], 201);
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let _: Map = other
+            .post(
+                &url,
+                &format!("/@me/games/{id}/accept"),
+                &serde_json::json!({}),
+            )
+            .await;
+        let res: Map = client.get(&url, &format!("/game/{id}")).await;
+        assert_eq!(res["message"]["time_control"]["seconds"], 1);
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        let mut rx = state.rooms.lock().unwrap()[&metadata.id].subscribe();
+        // Every update carries the time left, with White's clock running after Black moves.
+        super::play(&state, &metadata, 5, 4, Piece::Black)
+            .await
+            .unwrap();
+        let event = rx.recv().await.unwrap();
+        let EventData::GameUpdate {
+            clock: Some(times), ..
+        } = event.data()
+        else {
+            panic!("expected an update with the clock");
+        };
+        assert_eq!(times.turn, Piece::White);
+        assert!(times.white > 0 && times.white <= 1_000);
+        // Nobody sends anything, but the game still ends once White's time runs out.
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event.data(),
+            EventData::GameEnd {
+                result: GameResult {
+                    winner: Some(Piece::Black),
+                    reason: Reason::Timeout,
+                    ..
+                },
+                ..
+            }
+        ));
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        assert!(metadata.ended);
+        assert_eq!(metadata.reason.as_deref(), Some("timeout"));
+        assert_eq!(metadata.winner.as_deref(), Some(metadata.host.as_str()));
+        assert!(super::play(&state, &metadata, 3, 5, Piece::White)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn rematches() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let body = serde_json::json!({ "guest": guest, "color": "black", "width": 6 });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let endpoint = format!("/@me/games/{id}/accept");
+        let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+        let token = |name: String| {
+            let state = Arc::clone(&state);
+            async move {
+                let user = helpers::get_user(&state, &name, true).await.unwrap();
+                helpers::create_session(&state, &user, name).await.unwrap()
+            }
+        };
+        let (black, white) = (token(host).await, token(guest).await);
+        let packet = |op: u8, token: &str, kind: &str, id: &str| -> Packet {
+            serde_json::from_value(serde_json::json!({
+                "op": op,
+                "t": token,
+                "d": { "type": kind, "id": id },
+            }))
+            .unwrap()
+        };
+        // Both players join the game over their own sockets.
+        let mut sockets = vec![];
+        for token in [&black, &white] {
+            let (tx, rx) = tokio::sync::mpsc::channel(16);
+            let event = packet(3, token, "Join", &id)
+                .process(&state, Some(tx))
+                .await;
+            assert_eq!(event.op, EventKind::GameUpdate);
+            sockets.push(rx);
+        }
+        // A rematch can only be asked for once the game is over.
+        let event = packet(14, &black, "Rematch", &id)
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        packet(10, &white, "Resign", &id)
+            .process(&state, None)
+            .await;
+        let event = packet(14, &black, "Rematch", &id)
+            .process(&state, None)
+            .await;
+        assert_eq!(event.op, EventKind::Ack);
+        let event = packet(14, &white, "Rematch", &id)
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        // White hears about the rematch even though the finished game's room has closed.
+        let rematch = loop {
+            let event = sockets[1].recv().await.unwrap();
+            if let EventData::RematchOffer { id, piece } = event.data() {
+                assert_eq!(*piece, Piece::Black);
+                break id.to_string();
+            }
+        };
+        // Only the player who was offered the rematch can accept it.
+        let event = packet(15, &black, "AcceptRematch", &rematch)
+            .process(&state, None)
+            .await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        let event = packet(15, &white, "AcceptRematch", &rematch)
+            .process(&state, None)
+            .await;
+        assert_eq!(event.op, EventKind::Ack);
+        let metadata = helpers::get_game(&state, &rematch).await.unwrap();
+        assert_eq!(metadata.rematch_of.map(|id| id.to_string()), Some(id));
+        assert_eq!(metadata.width, 6);
+        assert!(!metadata.pending);
+        // The colors are swapped, so the old host now plays White.
+        assert_eq!(
+            helpers::piece(&metadata, &metadata.host),
+            Some(Piece::White)
+        );
+        // Both sockets are moved into the rematch without joining it.
+        for rx in &mut sockets {
+            loop {
+                if let EventData::RematchStart { id } = rx.recv().await.unwrap().data() {
+                    assert_eq!(id.to_string(), rematch);
+                    break;
+                }
+            }
+            assert_eq!(rx.recv().await.unwrap().op, EventKind::GameUpdate);
+        }
+        super::play(&state, &metadata, 3, 4, Piece::Black)
+            .await
+            .unwrap();
+        for rx in &mut sockets {
+            assert_eq!(rx.recv().await.unwrap().op, EventKind::GameUpdate);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simultaneous_rematches() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let res: Map = client
+            .post(&url, "/game", &serde_json::json!({ "guest": guest }))
+            .await;
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let endpoint = format!("/@me/games/{id}/accept");
+        let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+        let mut tokens = vec![];
+        for name in [host, guest] {
+            let user = helpers::get_user(&state, &name, true).await.unwrap();
+            tokens.push(helpers::create_session(&state, &user, name).await.unwrap());
+        }
+        let packet = |op: u8, token: &str, kind: &str| -> Packet {
+            let d = serde_json::json!({ "type": kind, "id": id });
+            serde_json::from_value(serde_json::json!({ "op": op, "t": token, "d": d })).unwrap()
+        };
+        packet(10, &tokens[0], "Resign").process(&state, None).await;
+        // Both players ask for a rematch at the same moment, but only one is created.
+        let requests = tokens.iter().map(|token| {
+            let (state, rematch) = (Arc::clone(&state), packet(14, token, "Rematch"));
+            tokio::spawn(async move { rematch.process(&state, None).await })
+        });
+        let mut acks = 0;
+        for request in requests.collect::<Vec<_>>() {
+            if request.await.unwrap().op == EventKind::Ack {
+                acks += 1;
+            }
+        }
+        assert_eq!(acks, 1);
+        let uuid = uuid::Uuid::parse_str(&id).unwrap();
+        let rematches = GameModel::find()
+            .filter(game::Column::RematchOf.eq(uuid))
+            .count(state.database.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(rematches, 1);
+    }
+
+    #[tokio::test]
+    async fn spectators() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        // The spectator befriends the host, so they need a new name on every run.
+        let spectator = format!("{}3{}", function!(), uuid::Uuid::now_v7().simple());
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let watcher = Client::authenticated(&[&spectator], &url, true).await;
+        let mut games = vec![];
+        for visibility in ["private", "friends"] {
+            let body = serde_json::json!({ "guest": guest, "visibility": visibility });
+            let res: Map = client.post(&url, "/game", &body).await;
+            assert_eq!(res["message"]["visibility"], visibility);
+            let id = res["message"]["id"].as_str().unwrap().to_string();
+            let endpoint = format!("/@me/games/{id}/accept");
+            let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+            games.push(id);
+        }
+        // Games for friends are hidden from anyone who isn't a friend of either player.
+        let res: Map = watcher.get(&url, &format!("/game/{}", games[1])).await;
+        assert_eq!(res["code"], 404);
+        let endpoint = format!("/users/{host}/friend");
+        let _: Map = watcher.post(&url, &endpoint, &serde_json::json!({})).await;
+        let endpoint = format!("/@me/friends/{spectator}/accept");
+        let res: Map = client.post(&url, &endpoint, &serde_json::json!({})).await;
+        assert_eq!(res["code"], 200);
+        let res: Map = watcher.get(&url, &format!("/game/{}", games[1])).await;
+        assert_eq!(res["code"], 200);
+        assert_eq!(res["message"]["spectators"], 0);
+        let user = helpers::get_user(&state, &spectator, true).await.unwrap();
+        let token = helpers::create_session(&state, &user, spectator)
+            .await
+            .unwrap();
+        let packet = |op: u8, d: serde_json::Value| -> Packet {
+            serde_json::from_value(serde_json::json!({ "op": op, "t": token, "d": d })).unwrap()
+        };
+        let spectate = |id: &str| packet(16, serde_json::json!({ "type": "Spectate", "id": id }));
+        // Private games can't be watched, even by friends.
+        let (tx, _) = tokio::sync::mpsc::channel(16);
+        let event = spectate(&games[0]).process(&state, Some(tx)).await;
+        assert!(matches!(event.data(), EventData::Error { code: 404, .. }));
+        let uuid = uuid::Uuid::parse_str(&games[1]).unwrap();
+        let mut room = state.rooms.lock().unwrap()[&uuid].subscribe();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let event = spectate(&games[1]).process(&state, Some(tx)).await;
+        assert_eq!(event.op, EventKind::GameUpdate);
+        assert!(matches!(
+            room.recv().await.unwrap().data(),
+            EventData::SpectatorCount { spectators: 1 }
+        ));
+        // Spectators see the game being played, but can't play in it themselves.
+        let place = serde_json::json!({ "type": "Place", "id": games[1], "x": 5, "y": 4 });
+        for op in [2, 7] {
+            let event = packet(op, place.clone()).process(&state, None).await;
+            assert!(matches!(event.data(), EventData::Error { code: 403, .. }));
+        }
+        let leave = serde_json::json!({ "type": "Leave", "id": games[1] });
+        let event = packet(4, leave).process(&state, None).await;
+        assert!(matches!(event.data(), EventData::Error { code: 403, .. }));
+        let metadata = helpers::get_game(&state, &games[1]).await.unwrap();
+        let black = helpers::players(&metadata).0.to_string();
+        let piece = helpers::piece(&metadata, &black).unwrap();
+        super::play(&state, &metadata, 5, 4, piece).await.unwrap();
+        loop {
+            if rx.recv().await.unwrap().op == EventKind::GameUpdate {
+                break;
+            }
+        }
+        assert_eq!(super::spectators(&state, uuid), 1);
+        // The count goes back down once the spectator's socket closes.
+        drop(rx);
+        loop {
+            if let EventData::SpectatorCount { spectators } = room.recv().await.unwrap().data() {
+                assert_eq!(*spectators, 0);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn chat() {
+        let database = sea_orm::Database::connect(server::TEST_DATABASE_URI)
+            .await
+            .unwrap();
+        let redis = redis::Client::open(server::TEST_REDIS_URI).unwrap();
+        let state = Arc::new(server::AppState::new(database, redis));
+        let url = test_utils::init(crate::server::app(Arc::clone(&state))).await;
+        let (host, guest) = (function!(), format!("{}2", function!()));
+        let outsider = format!("{}3", function!());
+        let client = Client::authenticated(&[&host], &url, true).await;
+        let other = Client::authenticated(&[&guest], &url, true).await;
+        let stranger = Client::authenticated(&[&outsider], &url, true).await;
+        let body = serde_json::json!({ "guest": guest });
+        let res: Map = client.post(&url, "/game", &body).await;
+        let id = res["message"]["id"].as_str().unwrap().to_string();
+        let endpoint = format!("/@me/games/{id}/accept");
+        let _: Map = other.post(&url, &endpoint, &serde_json::json!({})).await;
+        // Only the players, and anyone allowed to watch, can read the chat.
+        let res: Map = stranger.get(&url, &format!("/game/{id}/chat")).await;
+        assert_eq!(res["code"], 404);
+        let metadata = helpers::get_game(&state, &id).await.unwrap();
+        let (black, white) = helpers::players(&metadata);
+        let (black, white) = (black.to_string(), white.to_string());
+        let mut tokens = vec![];
+        for player in [&black, &white] {
+            let user = helpers::get_user(&state, player, false).await.unwrap();
+            let token = helpers::create_session(&state, &user, user.username.clone());
+            tokens.push((token.await.unwrap(), user.username));
+        }
+        let packet = |player: usize, op: u8, d: serde_json::Value| -> Packet {
+            let t = &tokens[player].0;
+            serde_json::from_value(serde_json::json!({ "op": op, "t": t, "d": d })).unwrap()
+        };
+        let chat = |player: usize, message: &str| {
+            let d = serde_json::json!({ "type": "Chat", "id": id, "message": message });
+            packet(player, 17, d)
+        };
+        let uuid = metadata.id;
+        let mut room = state.rooms.lock().unwrap()[&uuid].subscribe();
+        // White joins the game, so their socket gets everything said in the room.
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let join = serde_json::json!({ "type": "Join", "id": id });
+        let event = packet(1, 3, join).process(&state, Some(tx)).await;
+        assert_eq!(event.op, EventKind::GameUpdate);
+        let event = chat(0, "  good luck  ").process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+        assert!(matches!(
+            room.recv().await.unwrap().data(),
+            EventData::ChatMessage { author, piece: Piece::Black, message }
+                if *author == tokens[0].1 && message == "good luck"
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap().data(),
+            EventData::ChatMessage { .. }
+        ));
+        // Empty and overly long messages are rejected without being sent.
+        for message in ["   ".to_string(), "a".repeat(super::chat::MAX_LENGTH + 1)] {
+            let event = chat(0, &message).process(&state, None).await;
+            assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        }
+        // Once White mutes Black, Black's messages still reach the room but not White.
+        let mute = serde_json::json!({ "type": "Mute", "id": id });
+        let event = packet(1, 18, mute).process(&state, None).await;
+        assert_eq!(event.op, EventKind::Ack);
+        chat(0, "are you there?").process(&state, None).await;
+        assert!(matches!(
+            room.recv().await.unwrap().data(),
+            EventData::ChatMessage {
+                piece: Piece::Black,
+                ..
+            }
+        ));
+        chat(1, "you have a lot to say").process(&state, None).await;
+        assert!(matches!(
+            rx.recv().await.unwrap().data(),
+            EventData::ChatMessage {
+                piece: Piece::White,
+                ..
+            }
+        ));
+        // Players can only send a few messages at a time.
+        for _ in 0..4 {
+            let event = chat(1, "hi").process(&state, None).await;
+            assert_eq!(event.op, EventKind::Ack);
+        }
+        let event = chat(1, "hi").process(&state, None).await;
+        assert!(matches!(event.data(), EventData::Error { code: 429, .. }));
+        // Every message that was sent is saved for reading back later.
+        let history = super::chat::history(&state, uuid).await.unwrap();
+        assert_eq!(history.len(), 7);
+        assert_eq!(history[0].message, "good luck");
+        assert_eq!(history[0].author, black);
+        assert_eq!(history[0].color, "Black");
+        assert_eq!(history[2].author, white);
+        // The chat closes with the game, and its mutes and rate limits are forgotten.
+        let resign = serde_json::json!({ "type": "Resign", "id": id });
+        packet(0, 10, resign).process(&state, None).await;
+        let event = chat(0, "good game").process(&state, None).await;
+        assert!(matches!(event.data(), EventData::Error { code: 400, .. }));
+        assert!(state.mutes.lock().unwrap().is_empty());
+        assert!(state.chats.lock().unwrap().is_empty());
+        // White's socket is only kept around for a rematch until it closes.
+        drop(rx);
+        while state.sockets.lock().unwrap().contains_key(&uuid) {
+            tokio::task::yield_now().await;
+        }
+    }
+}
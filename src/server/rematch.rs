@@ -0,0 +1,136 @@
+use crate::{
+    server::{
+        bot, create_in_memory_game,
+        entities::{game, prelude::Game as GameModel},
+        handlers::StringError,
+        helpers,
+        packet::{self, Event, EventData, EventKind},
+        state::AppState,
+        strings,
+    },
+    Piece,
+};
+use axum::http::StatusCode;
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, QueryFilter};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// The rematch of the game with ID `id`, if one has been offered.
+pub async fn of(state: &AppState, id: Uuid) -> Result<Option<game::Model>, StringError> {
+    GameModel::find()
+        .filter(game::Column::RematchOf.eq(id))
+        .one(state.database.as_ref())
+        .await
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Creates a rematch of the finished game described by `metadata`, hosted by the player with
+/// ID `host`, in which both players play the other color. It keeps the board and time control
+/// of the original. Rematches against the bot are ready to start, while other rematches wait
+/// for the guest to accept them like any other invite.
+///
+/// Only one rematch is created per game, even if both players ask at once.
+pub async fn create(
+    state: &AppState,
+    metadata: &game::Model,
+    host: &str,
+) -> Result<game::Model, StringError> {
+    let _creating = state.rematches.lock().await;
+    if of(state, metadata.id).await?.is_some() {
+        return Err(StringError(
+            strings::REMATCH_EXISTS.into(),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let piece = helpers::piece(metadata, host)
+        .ok_or_else(|| StringError(strings::INVALID_GAME_ID.into(), StatusCode::NOT_FOUND))?;
+    let guest = if metadata.host == host {
+        &metadata.guest
+    } else {
+        &metadata.host
+    };
+    let model = game::ActiveModel {
+        id: ActiveValue::set(Uuid::now_v7()),
+        host: ActiveValue::set(host.to_string()),
+        guest: ActiveValue::set(guest.clone()),
+        pending: ActiveValue::set(metadata.bot.is_none()),
+        ended: ActiveValue::set(false),
+        bot: ActiveValue::set(metadata.bot.clone()),
+        host_black: ActiveValue::set(piece == Piece::White),
+        winner: ActiveValue::set(None),
+        black_score: ActiveValue::set(None),
+        white_score: ActiveValue::set(None),
+        reason: ActiveValue::set(None),
+        width: ActiveValue::set(metadata.width),
+        time_base: ActiveValue::set(metadata.time_base),
+        time_increment: ActiveValue::set(metadata.time_increment),
+        time_per_move: ActiveValue::set(metadata.time_per_move),
+        days_per_move: ActiveValue::set(metadata.days_per_move),
+        deadline: ActiveValue::set(None),
+        rematch_of: ActiveValue::set(Some(metadata.id)),
+        visibility: ActiveValue::set(metadata.visibility.clone()),
+    };
+    model
+        .insert(state.database.as_ref())
+        .await
+        .map_err(|e| StringError(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// Starts `rematch`, which has been accepted, and moves every socket that joined the game it
+/// is a rematch of into its room. Those sockets are told the rematch's ID and sent the new
+/// game, so players don't have to join it themselves.
+pub async fn start(state: &Arc<AppState>, rematch: &game::Model) -> Result<(), StringError> {
+    helpers::reset_deadline(state, rematch).await?;
+    create_in_memory_game(state, rematch);
+    let senders = rematch
+        .rematch_of
+        .and_then(|from| {
+            let mut sockets = state.sockets.lock().expect("mutex was poisoned");
+            sockets.remove(&from)
+        })
+        .unwrap_or_default();
+    let game = {
+        let games = state.games.lock().expect("mutex was poisoned");
+        games.get(&rematch.id).cloned()
+    };
+    let Some(game) = game else {
+        return Err(StringError(
+            strings::INVALID_GAME_ID.into(),
+            StatusCode::NOT_FOUND,
+        ));
+    };
+    // The players swap colors in the rematch.
+    for (piece, sender) in senders
+        .into_iter()
+        .filter(|(_, sender)| !sender.is_closed())
+    {
+        let start = Event::new(
+            EventKind::RematchStart,
+            EventData::RematchStart { id: rematch.id },
+        );
+        let _ = sender.send(start).await;
+        let _ = sender.send(packet::update(state, rematch.id, &game)).await;
+        packet::subscribe(state, rematch.id, !piece, sender);
+    }
+    // The bot starts playing once everyone is in the room, in case it moves first.
+    if let Some(difficulty) = bot::difficulty(rematch) {
+        bot::spawn(Arc::clone(state), rematch.clone(), difficulty);
+    }
+    Ok(())
+}
+
+/// Sends `event` to every socket that joined the game with ID `uuid`, even once the game's
+/// room has closed. Sockets that have since disconnected are forgotten.
+pub async fn notify(state: &AppState, uuid: Uuid, event: Event) {
+    let senders = {
+        let mut sockets = state.sockets.lock().expect("mutex was poisoned");
+        let Some(senders) = sockets.get_mut(&uuid) else {
+            return;
+        };
+        senders.retain(|(_, sender)| !sender.is_closed());
+        senders.clone()
+    };
+    for (_, sender) in senders {
+        let _ = sender.send(event.clone()).await;
+    }
+}
@@ -1,19 +1,51 @@
-use crate::{server::packet::Event, Game};
+use crate::{
+    server::{clock::Clock, packet::Event},
+    Book, Game, Piece,
+};
 use sea_orm::DatabaseConnection;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     sync::{Arc, Mutex},
 };
-use tokio::sync::broadcast;
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::Instant,
+};
 use uuid::Uuid;
 
+/// The ID of a member watching a game, and the socket they're watching from.
+pub type Spectator = (String, mpsc::Sender<Event>);
+
+/// The color a player is playing, and a socket they joined the game from.
+pub type Player = (Piece, mpsc::Sender<Event>);
+
+/// When a player sent their recent chat messages in a game, oldest first.
+pub type Sent = VecDeque<Instant>;
+
 #[derive(Clone)]
 #[allow(clippy::module_name_repetitions)] // This seems fine
 pub struct AppState {
     pub(super) games: Arc<Mutex<HashMap<Uuid, Game>>>,
     pub(super) rooms: Arc<Mutex<HashMap<Uuid, broadcast::Sender<Event>>>>,
+    /// The color of the player waiting for an answer to a takeback request in each game.
+    pub(super) takebacks: Arc<Mutex<HashMap<Uuid, Piece>>>,
+    /// The color of the player waiting for an answer to a draw offer in each game.
+    pub(super) draws: Arc<Mutex<HashMap<Uuid, Piece>>>,
+    /// The clocks of games being played with a time control.
+    pub(super) clocks: Arc<Mutex<HashMap<Uuid, Clock>>>,
+    /// The sockets that joined each game, so that they can be moved into its rematch.
+    pub(super) sockets: Arc<Mutex<HashMap<Uuid, Vec<Player>>>>,
+    /// The members watching each game, with the sockets they're watching it from.
+    pub(super) spectators: Arc<Mutex<HashMap<Uuid, Vec<Spectator>>>>,
+    /// Held while checking for and creating a rematch, which takes a trip to the database.
+    pub(super) rematches: Arc<tokio::sync::Mutex<()>>,
+    /// When each player last sent chat messages in each game, for rate limiting.
+    pub(super) chats: Arc<Mutex<HashMap<(Uuid, Piece), Sent>>>,
+    /// The colors of the players in each game who have muted their opponent.
+    pub(super) mutes: Arc<Mutex<HashMap<Uuid, HashSet<Piece>>>>,
     pub(super) database: Arc<DatabaseConnection>,
     pub(super) redis: Arc<redis::Client>,
+    pub(super) book: Arc<Book>,
 }
 
 impl AppState {
@@ -22,8 +54,26 @@ impl AppState {
         Self {
             games: Arc::new(Mutex::new(HashMap::new())),
             rooms: Arc::new(Mutex::new(HashMap::new())),
+            takebacks: Arc::new(Mutex::new(HashMap::new())),
+            draws: Arc::new(Mutex::new(HashMap::new())),
+            clocks: Arc::new(Mutex::new(HashMap::new())),
+            sockets: Arc::new(Mutex::new(HashMap::new())),
+            spectators: Arc::new(Mutex::new(HashMap::new())),
+            rematches: Arc::new(tokio::sync::Mutex::new(())),
+            chats: Arc::new(Mutex::new(HashMap::new())),
+            mutes: Arc::new(Mutex::new(HashMap::new())),
             database: Arc::new(database),
             redis: Arc::new(redis),
+            book: Arc::new(Book::openings()),
+        }
+    }
+
+    /// Uses `book` for the companion's opening moves instead of the bundled book.
+    #[must_use]
+    pub fn with_book(self, book: Book) -> Self {
+        Self {
+            book: Arc::new(book),
+            ..self
         }
     }
 }
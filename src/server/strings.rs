@@ -11,6 +11,27 @@ pub const INVALID_USERNAME: &str =
 pub const ALREADY_FRIENDS: &str = "You're already friends with that user!";
 pub const FRIEND_SELF: &str = "You can't friend yourself!";
 pub const GAME_SELF: &str = "You can't create a game with yourself!";
+pub const GAME_OPPONENT: &str = "Choose either another player or the computer to play against.";
+pub const WRONG_COLOR: &str = "You can only place your own color.";
+pub const INVALID_MOVE_HISTORY: &str = "The recorded moves for this game are not a legal game.";
+pub const NO_TAKEBACK: &str = "You haven't made a move that can be taken back.";
+pub const NO_TAKEBACK_REQUEST: &str = "Your opponent hasn't asked to take back a move.";
+pub const NO_DRAW_OFFER: &str = "Your opponent hasn't offered a draw.";
+pub const GAME_OVER: &str = "The game is already over.";
+pub const GAME_NOT_OVER: &str = "You can only ask for a rematch once the game is over.";
+pub const REMATCH_EXISTS: &str = "A rematch of this game has already been offered.";
+pub const SPECTATOR: &str = "Spectators can only watch the game.";
+pub const SPECTATE_OWN_GAME: &str = "You're playing in this game, so join it instead.";
+pub const NO_REMATCH: &str = "There's no rematch waiting for you to accept.";
+pub const OUT_OF_TIME: &str = "You ran out of time.";
+pub const INVALID_TIME_CONTROL: &str = "Time controls need a positive amount of time.";
+pub const CORRESPONDENCE_CLOCK: &str =
+    "Correspondence games give days per move, so they can't have a clock as well.";
+pub const CHAT_EMPTY: &str = "Chat messages can't be empty.";
+pub const CHAT_TOO_LONG: &str = "Chat messages can be at most 500 characters long.";
+pub const CHAT_RATE_LIMITED: &str =
+    "You're sending messages too quickly. Wait a moment and try again.";
+pub const NO_MOVES: &str = "There are no legal moves to analyze.";
 pub const RESERVED_OPCODE: &str = "Reserved opcode: no action";
 
 // -- internal --
@@ -0,0 +1,181 @@
+use crate::board::{Board, Piece, MAX_SQUARES};
+
+/// The number of entries in a table created with [`TranspositionTable::default`].
+const DEFAULT_ENTRIES: usize = 1 << 16;
+
+/// A random key for each color on each square, indexed by `[piece][x + y * width]`.
+const KEYS: [[u64; MAX_SQUARES]; 2] = keys();
+/// Mixed into the hash of positions where White is to move.
+const WHITE_TO_MOVE: u64 = splitmix(u64::MAX);
+
+/// Generates the Zobrist keys at compile time so that hashes are stable between runs.
+const fn keys() -> [[u64; MAX_SQUARES]; 2] {
+    let mut keys = [[0; MAX_SQUARES]; 2];
+    let mut i = 0;
+    while i < 2 * MAX_SQUARES {
+        keys[i / MAX_SQUARES][i % MAX_SQUARES] = splitmix(i as u64);
+        i += 1;
+    }
+    keys
+}
+
+/// The `SplitMix64` finalizer, which spreads consecutive inputs across all 64 bits.
+const fn splitmix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The Zobrist hash of `board` with `player` to move.
+#[must_use]
+pub fn zobrist(board: &Board, player: Piece) -> u64 {
+    let mut hash = match player {
+        Piece::Black => 0,
+        Piece::White => WHITE_TO_MOVE,
+    };
+    for piece in [Piece::Black, Piece::White] {
+        let mut bits = board.pieces(piece);
+        while bits != 0 {
+            hash ^= KEYS[piece as usize][bits.trailing_zeros() as usize];
+            bits &= bits - 1;
+        }
+    }
+    hash
+}
+
+/// How a stored score relates to the true value of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bound {
+    /// The score is the true value.
+    Exact,
+    /// The search failed high, so the true value is at least the score.
+    Lower,
+    /// The search failed low, so the true value is at most the score.
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub key: u64,
+    pub depth: usize,
+    pub bound: Bound,
+    pub score: isize,
+    pub best: Option<(usize, usize)>,
+}
+
+/// A fixed-size cache of search results, keyed by the Zobrist hash of each position.
+///
+/// A table can be shared between searches of positions from the same game, but only by
+/// companions using the same evaluator, since scores from different evaluators can't be
+/// compared.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+    /// Creates an empty table with room for `entries` positions, rounded down to a power of
+    /// two.
+    ///
+    /// # Panics
+    /// Panics if `entries` is zero.
+    #[must_use]
+    pub fn new(entries: usize) -> Self {
+        assert!(
+            entries > 0,
+            "a transposition table needs at least one entry"
+        );
+        let entries = 1 << entries.ilog2();
+        Self {
+            entries: vec![None; entries],
+        }
+    }
+
+    /// Removes every stored position.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// The stored result for the position with hash `key`, if it hasn't been overwritten.
+    pub(crate) fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.index(key)].filter(|entry| entry.key == key)
+    }
+
+    /// Stores a search result, replacing whichever position was in its slot before. Older
+    /// positions are rarely useful again since discs are never removed from the board.
+    pub(crate) fn store(&mut self, entry: Entry) {
+        let index = self.index(entry.key);
+        self.entries[index] = Some(entry);
+    }
+
+    #[allow(clippy::cast_possible_truncation)] // The table is indexed by the low bits only
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{zobrist, Bound, Entry, TranspositionTable};
+    use crate::{Board, Piece};
+
+    #[test]
+    fn transpositions() {
+        // The same position reached by playing the same moves in a different order.
+        let mut first = Board::new();
+        for (x, y, piece) in [
+            (5, 4, Piece::Black),
+            (5, 5, Piece::White),
+            (2, 3, Piece::Black),
+            (2, 2, Piece::White),
+        ] {
+            assert_ne!(first.place(x, y, piece), 0);
+        }
+        let mut second = Board::new();
+        for (x, y, piece) in [
+            (2, 3, Piece::Black),
+            (2, 2, Piece::White),
+            (5, 4, Piece::Black),
+            (5, 5, Piece::White),
+        ] {
+            assert_ne!(second.place(x, y, piece), 0);
+        }
+        assert_eq!(first, second);
+        assert_eq!(
+            zobrist(&first, Piece::Black),
+            zobrist(&second, Piece::Black)
+        );
+        assert_ne!(zobrist(&first, Piece::Black), zobrist(&first, Piece::White));
+        assert_ne!(
+            zobrist(&first, Piece::Black),
+            zobrist(&Board::new(), Piece::Black)
+        );
+    }
+
+    #[test]
+    fn table() {
+        let mut table = TranspositionTable::new(100);
+        assert_eq!(table.entries.len(), 64);
+        let entry = Entry {
+            key: 3,
+            depth: 4,
+            bound: Bound::Exact,
+            score: 10,
+            best: Some((2, 3)),
+        };
+        table.store(entry);
+        assert_eq!(table.probe(3), Some(entry));
+        // A different position that maps to the same slot.
+        assert_eq!(table.probe(67), None);
+        table.store(Entry { key: 67, ..entry });
+        assert_eq!(table.probe(3), None);
+        table.clear();
+        assert_eq!(table.probe(67), None);
+    }
+}
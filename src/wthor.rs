@@ -0,0 +1,404 @@
+//! Reading and writing the binary WTHOR databases published by the Fédération Française
+//! d'Othello, which hold tens of thousands of tournament games.
+//!
+//! Games are kept in `.wtb` files, one file per year, and refer to players by their index in
+//! a separate `.jou` file. Both start with the same 16-byte header. Every number is stored
+//! little-endian.
+
+use crate::{Game, GameResult, Piece, PlaceError, Reason, Record};
+
+/// The width of the board, which is always a standard one in practice.
+const WIDTH: usize = 8;
+/// The length of the header at the start of every file.
+const HEADER: usize = 16;
+/// The length of each game in a `.wtb` file: six bytes of player and tournament numbers, two
+/// scores, then 60 moves.
+const GAME: usize = 68;
+/// The most moves a game can have, since passes aren't recorded.
+const MOVES: usize = 60;
+/// The length of each name in a `.jou` file, including the terminating NUL.
+const NAME: usize = 20;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum WthorError {
+    #[error("the file is too short, at {0} bytes")]
+    Length(usize),
+    #[error("games on {0}x{0} boards are not supported")]
+    Unsupported(u8),
+    #[error("game {0}: {1} is not a move")]
+    Square(usize, u8),
+    #[error("game {0}, move {1}: {2}")]
+    Move(usize, usize, PlaceError),
+    #[error("there is no player number {0}")]
+    Player(u16),
+}
+
+/// The date a file was created, as a year, month and day.
+pub type Date = (u16, u8, u8);
+
+/// A `.wtb` file of games.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Database {
+    pub created: Date,
+    /// The year the games were played in.
+    pub year: u16,
+    /// How many empty squares were left when the theoretical scores were worked out.
+    pub depth: u8,
+    pub games: Vec<Entry>,
+}
+
+/// A game in a [`Database`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub tournament: u16,
+    /// The number of the player with the black discs in the [`Players`] file.
+    pub black: u16,
+    /// The number of the player with the white discs in the [`Players`] file.
+    pub white: u16,
+    /// The number of black discs at the end of the game, with any empty squares counted for
+    /// the winner.
+    pub score: u8,
+    /// The number of black discs at the end of the game had both players played perfectly
+    /// from `depth` empty squares on.
+    pub theoretical: u8,
+    pub game: Game,
+}
+
+/// A `.jou` file of player names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Players {
+    pub created: Date,
+    pub names: Vec<String>,
+}
+
+impl Database {
+    /// Reads a `.wtb` file.
+    ///
+    /// # Errors
+    /// Returns an error if the file is truncated, holds games on a board other than 8x8, or
+    /// contains an illegal move.
+    pub fn read(bytes: &[u8]) -> Result<Self, WthorError> {
+        let header = Header::read(bytes)?;
+        if header.size != 0 && usize::from(header.size) != WIDTH {
+            return Err(WthorError::Unsupported(header.size));
+        }
+        let games = (0..usize::try_from(header.games).unwrap_or(usize::MAX))
+            .map(|i| {
+                let start = HEADER + i * GAME;
+                let game = bytes
+                    .get(start..start + GAME)
+                    .ok_or(WthorError::Length(bytes.len()))?;
+                Entry::read(i + 1, game)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            created: header.created,
+            year: header.year,
+            depth: header.depth,
+            games,
+        })
+    }
+
+    /// Writes the database as a `.wtb` file.
+    ///
+    /// # Panics
+    /// Panics if there are more games than the format can count.
+    #[must_use]
+    pub fn write(&self) -> Vec<u8> {
+        let mut bytes = Header {
+            created: self.created,
+            games: u32::try_from(self.games.len()).expect("too many games for a WTHOR file"),
+            records: 0,
+            year: self.year,
+            size: 0,
+            depth: self.depth,
+        }
+        .write();
+        for entry in &self.games {
+            entry.write(&mut bytes);
+        }
+        bytes
+    }
+}
+
+impl Entry {
+    /// An entry for `game`, whose score is worked out from the board. Its theoretical score
+    /// is taken to be the same, since working it out needs a search.
+    ///
+    /// # Panics
+    /// Panics if `game` isn't played on an 8x8 board, which is all the format can hold.
+    #[must_use]
+    pub fn new(game: Game, tournament: u16, black: u16, white: u16) -> Self {
+        assert_eq!(
+            game.width(),
+            WIDTH,
+            "WTHOR games are played on an 8x8 board"
+        );
+        let (discs, others) = game.score();
+        let empties = WIDTH * WIDTH - discs - others;
+        // Empty squares go to the winner.
+        let discs = match discs.cmp(&others) {
+            std::cmp::Ordering::Greater => discs + empties,
+            std::cmp::Ordering::Less => discs,
+            std::cmp::Ordering::Equal => discs + empties / 2,
+        };
+        let score = u8::try_from(discs).expect("the board has 64 squares");
+        Self {
+            tournament,
+            black,
+            white,
+            score,
+            theoretical: score,
+            game,
+        }
+    }
+
+    /// The game as a [`Record`], with the names of its players looked up in `players`. The
+    /// score in the result is taken from the board. Games that weren't played out are put
+    /// down to resignation, which is all the format allows for.
+    ///
+    /// # Errors
+    /// Returns an error if either player isn't in `players`.
+    pub fn record(&self, players: &Players) -> Result<Record, WthorError> {
+        let name = |number: u16| {
+            players
+                .names
+                .get(usize::from(number))
+                .cloned()
+                .ok_or(WthorError::Player(number))
+        };
+        let half = WIDTH * WIDTH / 2;
+        let winner = match usize::from(self.score).cmp(&half) {
+            std::cmp::Ordering::Greater => Some(Piece::Black),
+            std::cmp::Ordering::Less => Some(Piece::White),
+            std::cmp::Ordering::Equal => None,
+        };
+        Ok(Record {
+            black: name(self.black)?,
+            white: name(self.white)?,
+            game: self.game.clone(),
+            result: Some(GameResult {
+                winner,
+                score: self.game.score(),
+                reason: self
+                    .game
+                    .result()
+                    .map_or(Reason::Resignation, |result| result.reason),
+            }),
+        })
+    }
+
+    fn read(number: usize, bytes: &[u8]) -> Result<Self, WthorError> {
+        let mut game = Game::new();
+        let moves = bytes[8..].iter().take_while(|&&square| square != 0);
+        for (i, &square) in moves.enumerate() {
+            let (x, y) = (usize::from(square % 10), usize::from(square / 10));
+            if !(1..=8).contains(&x) || !(1..=8).contains(&y) {
+                return Err(WthorError::Square(number, square));
+            }
+            game.place(x - 1, y - 1, game.turn())
+                .map_err(|e| WthorError::Move(number, i + 1, e))?;
+        }
+        Ok(Self {
+            tournament: u16::from_le_bytes([bytes[0], bytes[1]]),
+            black: u16::from_le_bytes([bytes[2], bytes[3]]),
+            white: u16::from_le_bytes([bytes[4], bytes[5]]),
+            score: bytes[6],
+            theoretical: bytes[7],
+            game,
+        })
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.tournament.to_le_bytes());
+        bytes.extend(self.black.to_le_bytes());
+        bytes.extend(self.white.to_le_bytes());
+        bytes.extend([self.score, self.theoretical]);
+        let mut moves: Vec<u8> = self
+            .game
+            .history()
+            .into_iter()
+            .flatten()
+            .map(|(x, y)| u8::try_from(10 * (y + 1) + x + 1).expect("squares fit in a byte"))
+            .collect();
+        moves.resize(MOVES, 0);
+        bytes.extend(moves);
+    }
+}
+
+impl Players {
+    /// Reads a `.jou` file. Names are stored in Latin-1.
+    ///
+    /// # Errors
+    /// Returns an error if the file is truncated.
+    pub fn read(bytes: &[u8]) -> Result<Self, WthorError> {
+        let header = Header::read(bytes)?;
+        let names = (0..usize::from(header.records))
+            .map(|i| {
+                let start = HEADER + i * NAME;
+                let name = bytes
+                    .get(start..start + NAME)
+                    .ok_or(WthorError::Length(bytes.len()))?;
+                Ok(name
+                    .iter()
+                    .take_while(|&&byte| byte != 0)
+                    .map(|&byte| char::from(byte))
+                    .collect())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            created: header.created,
+            names,
+        })
+    }
+
+    /// Writes the names as a `.jou` file. Names that are too long, or have characters outside
+    /// of Latin-1, are cut short.
+    ///
+    /// # Panics
+    /// Panics if there are more names than the format can count.
+    #[must_use]
+    pub fn write(&self) -> Vec<u8> {
+        let mut bytes = Header {
+            created: self.created,
+            games: 0,
+            records: u16::try_from(self.names.len()).expect("too many players for a WTHOR file"),
+            year: 0,
+            size: 0,
+            depth: 0,
+        }
+        .write();
+        for name in &self.names {
+            let mut latin: Vec<u8> = name
+                .chars()
+                .map_while(|c| u8::try_from(c).ok())
+                .take(NAME - 1)
+                .collect();
+            latin.resize(NAME, 0);
+            bytes.extend(latin);
+        }
+        bytes
+    }
+}
+
+/// The header at the start of both kinds of file.
+struct Header {
+    created: Date,
+    /// The number of games in a `.wtb` file.
+    games: u32,
+    /// The number of names in a `.jou` file.
+    records: u16,
+    year: u16,
+    /// The size of the board, where zero also means 8x8.
+    size: u8,
+    depth: u8,
+}
+
+impl Header {
+    fn read(bytes: &[u8]) -> Result<Self, WthorError> {
+        let bytes = bytes.get(..HEADER).ok_or(WthorError::Length(bytes.len()))?;
+        Ok(Self {
+            created: (
+                u16::from(bytes[0]) * 100 + u16::from(bytes[1]),
+                bytes[2],
+                bytes[3],
+            ),
+            games: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            records: u16::from_le_bytes([bytes[8], bytes[9]]),
+            year: u16::from_le_bytes([bytes[10], bytes[11]]),
+            size: bytes[12],
+            depth: bytes[14],
+        })
+    }
+
+    fn write(&self) -> Vec<u8> {
+        let (year, month, day) = self.created;
+        let century = u8::try_from(year / 100).unwrap_or(u8::MAX);
+        let year = u8::try_from(year % 100).expect("less than 100");
+        let mut bytes = vec![century, year, month, day];
+        bytes.extend(self.games.to_le_bytes());
+        bytes.extend(self.records.to_le_bytes());
+        bytes.extend(self.year.to_le_bytes());
+        // The game type and a reserved byte are always zero.
+        bytes.extend([self.size, 0, self.depth, 0]);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Database, Entry, Players, WthorError};
+    use crate::{Game, Piece, PlaceError, Reason};
+
+    const GAMES: &[u8] = include_bytes!("../data/sample.wtb");
+    const PLAYERS: &[u8] = include_bytes!("../data/sample.jou");
+
+    #[test]
+    fn sample() {
+        let database = Database::read(GAMES).unwrap();
+        assert_eq!(database.created, (2024, 11, 5));
+        assert_eq!((database.year, database.depth), (2003, 22));
+        assert_eq!(database.games.len(), 3);
+        let players = Players::read(PLAYERS).unwrap();
+        assert_eq!(players.names, ["Saio1200", "Saio3000", "Zebra", "Edax"]);
+        let record = database.games[0].record(&players).unwrap();
+        assert_eq!(
+            (record.black.as_str(), record.white.as_str()),
+            ("Saio1200", "Saio3000")
+        );
+        assert_eq!(record.game.score(), (43, 21));
+        assert_eq!(record.result.unwrap().winner, Some(Piece::Black));
+        assert_eq!(record.result.unwrap().reason, Reason::BoardFull);
+        // The last game was cut short, and the score says that White won it.
+        let record = database.games[2].record(&players).unwrap();
+        assert_eq!(record.game, Game::from_transcript("f5d6c3d3c4").unwrap());
+        assert_eq!(record.result.unwrap().winner, Some(Piece::White));
+        assert_eq!(record.result.unwrap().reason, Reason::Resignation);
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(Database::read(GAMES).unwrap().write(), GAMES);
+        assert_eq!(Players::read(PLAYERS).unwrap().write(), PLAYERS);
+        // Entries made from games match the ones in the file.
+        let database = Database::read(GAMES).unwrap();
+        let first = &database.games[0];
+        let entry = Entry::new(first.game.clone(), 0, 0, 1);
+        assert_eq!(entry.score, first.score);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Database::read(&GAMES[..100]).unwrap_err(),
+            WthorError::Length(100)
+        );
+        let mut bytes = GAMES.to_vec();
+        bytes[12] = 10;
+        assert_eq!(
+            Database::read(&bytes).unwrap_err(),
+            WthorError::Unsupported(10)
+        );
+        // The first game's second move, overwritten with its first.
+        let mut bytes = GAMES.to_vec();
+        bytes[16 + 9] = bytes[16 + 8];
+        assert_eq!(
+            Database::read(&bytes).unwrap_err(),
+            WthorError::Move(1, 2, PlaceError::Occupied(4, 5))
+        );
+        bytes[16 + 9] = 99;
+        assert_eq!(
+            Database::read(&bytes).unwrap_err(),
+            WthorError::Square(1, 99)
+        );
+        let players = Players {
+            created: (2024, 11, 5),
+            names: vec![],
+        };
+        let database = Database::read(GAMES).unwrap();
+        assert_eq!(
+            database.games[0].record(&players).unwrap_err(),
+            WthorError::Player(0)
+        );
+    }
+}
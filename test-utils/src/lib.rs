@@ -67,7 +67,7 @@ impl Client {
     pub async fn get<D: DeserializeOwned>(&self, url: &str, endpoint: &str) -> D {
         let res = self
             .inner
-            .get(&format!("{url}{endpoint}"))
+            .get(format!("{url}{endpoint}"))
             .send()
             .await
             .unwrap();
@@ -83,7 +83,7 @@ impl Client {
     ) -> D {
         let res = self
             .inner
-            .post(&format!("{url}{endpoint}"))
+            .post(format!("{url}{endpoint}"))
             .header("Content-Type", "application/json")
             .body(serde_json::to_string(&body).unwrap())
             .send()